@@ -0,0 +1,122 @@
+use std::ops::{Add, Mul, Sub};
+
+// How a value moves from one keyframe to the next.
+#[derive(Copy, Clone, PartialEq)]
+pub enum Interpolation {
+    // Holds the value until the next key.
+    Step,
+    Linear,
+    // Eases through the keys along a cubic curve whose slope at each key
+    // points from the key before to the key after. It comes to rest at the
+    // first and last keys.
+    Bezier,
+}
+
+struct Key<T> {
+    time: f64,
+    value: T,
+    interpolation: Interpolation,
+}
+
+// A value changing over time, given by keys which are interpolated between.
+// Before the first key and after the last the value holds still.
+pub struct Track<T> {
+    keys: Vec<Key<T>>,
+}
+
+impl<T> Default for Track<T> {
+    fn default() -> Self {
+        Track { keys: vec![] }
+    }
+}
+
+impl<T> Track<T>
+where
+    T: Copy + Add<Output = T> + Sub<Output = T> + Mul<f64, Output = T>,
+{
+    pub fn new() -> Self {
+        Track::default()
+    }
+
+    // Adds a key, with the interpolation used from it to the next one. A key
+    // at the same time as another replaces it.
+    pub fn add_key(&mut self, time: f64, value: T, interpolation: Interpolation) {
+        let key = Key {
+            time,
+            value,
+            interpolation,
+        };
+        match self.keys.iter().position(|key| key.time >= time) {
+            Some(index) if self.keys[index].time == time => self.keys[index] = key,
+            Some(index) => self.keys.insert(index, key),
+            None => self.keys.push(key),
+        }
+    }
+
+    pub fn value_at(&self, time: f64) -> Option<T> {
+        let index = match self.keys.iter().position(|key| key.time > time) {
+            None => return self.keys.last().map(|key| key.value),
+            Some(0) => return Some(self.keys[0].value),
+            Some(next) => next - 1,
+        };
+        let (from, to) = (&self.keys[index], &self.keys[index + 1]);
+
+        let span = to.time - from.time;
+        let t = (time - from.time) / span;
+        Some(match from.interpolation {
+            Interpolation::Step => from.value,
+            Interpolation::Linear => from.value + (to.value - from.value) * t,
+            Interpolation::Bezier => {
+                let start = from.value + self.slope(index) * (span / 3.0);
+                let end = to.value - self.slope(index + 1) * (span / 3.0);
+                bezier(from.value, start, end, to.value, t)
+            }
+        })
+    }
+
+    // Rate of change through a key, from its neighbours on either side.
+    fn slope(&self, index: usize) -> T {
+        let key = &self.keys[index];
+        if index == 0 || index == self.keys.len() - 1 {
+            return key.value * 0.0;
+        }
+        let (before, after) = (&self.keys[index - 1], &self.keys[index + 1]);
+        (after.value - before.value) * (1.0 / (after.time - before.time))
+    }
+}
+
+fn bezier<T>(p0: T, p1: T, p2: T, p3: T, t: f64) -> T
+where
+    T: Copy + Add<Output = T> + Mul<f64, Output = T>,
+{
+    let s = 1.0 - t;
+    p0 * (s * s * s) + p1 * (3.0 * s * s * t) + p2 * (3.0 * s * t * t) + p3 * (t * t * t)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use nalgebra::Vector3;
+
+    #[test]
+    fn tracks_interpolate_between_keys() {
+        let mut linear = Track::new();
+        linear.add_key(2.0, Vector3::new(4.0, 0.0, 0.0), Interpolation::Linear);
+        linear.add_key(0.0, Vector3::new(0.0, 2.0, 0.0), Interpolation::Linear);
+        assert_eq!(linear.value_at(-1.0), Some(Vector3::new(0.0, 2.0, 0.0)));
+        assert_eq!(linear.value_at(1.0), Some(Vector3::new(2.0, 1.0, 0.0)));
+        assert_eq!(linear.value_at(3.0), Some(Vector3::new(4.0, 0.0, 0.0)));
+
+        let mut eased = Track::new();
+        eased.add_key(0.0, 0.0, Interpolation::Bezier);
+        eased.add_key(1.0, 1.0, Interpolation::Bezier);
+        eased.add_key(2.0, 2.0, Interpolation::Step);
+        eased.add_key(3.0, 5.0, Interpolation::Linear);
+        assert!(eased.value_at(0.1).unwrap() < 0.1);
+        assert!((eased.value_at(1.0).unwrap() - 1.0).abs() < 1e-12);
+        let middle = eased.value_at(1.5).unwrap();
+        assert!(middle > 1.0 && middle < 2.0);
+        assert_eq!(eased.value_at(2.5), Some(2.0));
+        assert!(Track::<f64>::new().value_at(0.0).is_none());
+    }
+}
@@ -0,0 +1,167 @@
+use nalgebra::Point2;
+use std::f64;
+use std::rc::Rc;
+
+// The shape of the opening in a lens, which out of focus highlights take on.
+#[derive(Clone)]
+pub enum Aperture {
+    Circle,
+    // A number of straight blades, turned by an angle in degrees.
+    Polygon(usize, f64),
+    Image(Rc<BokehImage>),
+}
+
+impl Aperture {
+    pub fn polygon(blades: usize, rotation: f64) -> Self {
+        Aperture::Polygon(blades.max(3), rotation.to_radians())
+    }
+
+    // A random point spread evenly over the opening, which fits inside the
+    // unit circle.
+    pub fn sample(&self) -> Point2<f64> {
+        match self {
+            Aperture::Circle => {
+                let r = rand::random::<f64>().sqrt();
+                let angle = rand::random::<f64>() * f64::consts::PI * 2.0;
+                Point2::new(r * angle.cos(), r * angle.sin())
+            }
+            Aperture::Polygon(blades, rotation) => {
+                // Every blade edge makes an equal triangle with the centre.
+                let step = 2.0 * f64::consts::PI / *blades as f64;
+                let blade = (rand::random::<f64>() * *blades as f64) as usize % blades;
+                let start = rotation + step * blade as f64;
+                let (a, b) = (rand::random::<f64>(), rand::random::<f64>());
+                let (a, b) = if a + b > 1.0 { (1.0 - a, 1.0 - b) } else { (a, b) };
+                Point2::new(
+                    a * start.cos() + b * (start + step).cos(),
+                    a * start.sin() + b * (start + step).sin(),
+                )
+            }
+            Aperture::Image(image) => image.sample(),
+        }
+    }
+}
+
+// A greyscale picture of the opening, sampled in proportion to its
+// brightness.
+pub struct BokehImage {
+    width: usize,
+    height: usize,
+    cdf: Vec<f64>,
+}
+
+impl BokehImage {
+    // Values run along each row from the top left.
+    pub fn new(width: usize, height: usize, values: &[f64]) -> Option<Self> {
+        if width == 0 || height == 0 || Some(values.len()) != width.checked_mul(height) {
+            return None;
+        }
+
+        let cdf: Vec<f64> = values
+            .iter()
+            .scan(0.0, |total, value| {
+                *total += value.max(0.0);
+                Some(*total)
+            })
+            .collect();
+        if cdf[cdf.len() - 1] <= 0.0 {
+            return None;
+        }
+        Some(BokehImage { width, height, cdf })
+    }
+
+    // Reads a greyscale PGM image, either plain (P2) or raw (P5) with one
+    // or two bytes for each pixel.
+    pub fn from_pgm(bytes: &[u8]) -> Option<Self> {
+        // The header is four words, which may have comments between them,
+        // and then a single whitespace character before any raw pixels.
+        let mut words = vec![];
+        let mut index = 0;
+        while words.len() < 4 {
+            while bytes.get(index)?.is_ascii_whitespace() || bytes[index] == b'#' {
+                if bytes[index] == b'#' {
+                    while *bytes.get(index)? != b'\n' {
+                        index += 1;
+                    }
+                }
+                index += 1;
+            }
+            let start = index;
+            while index < bytes.len() && !bytes[index].is_ascii_whitespace() {
+                index += 1;
+            }
+            words.push(std::str::from_utf8(&bytes[start..index]).ok()?);
+        }
+        let number = |word: &str| word.parse::<usize>().ok();
+        let (width, height, max) = (number(words[1])?, number(words[2])?, number(words[3])?);
+        if max == 0 || max > 65535 {
+            return None;
+        }
+
+        let values: Vec<f64> = match words[0] {
+            "P2" => std::str::from_utf8(&bytes[index..])
+                .ok()?
+                .split_whitespace()
+                .map(|word| number(word).map(|value| value as f64 / max as f64))
+                .collect::<Option<_>>()?,
+            "P5" => {
+                let pixels = bytes.get(index + 1..)?;
+                if max < 256 {
+                    pixels.iter().map(|value| *value as f64 / max as f64).collect()
+                } else {
+                    pixels
+                        .chunks(2)
+                        .map(|pair| pair.get(1).map(|low| (pair[0] as usize * 256 + *low as usize) as f64 / max as f64))
+                        .collect::<Option<_>>()?
+                }
+            }
+            _ => return None,
+        };
+        BokehImage::new(width, height, &values)
+    }
+
+    fn sample(&self) -> Point2<f64> {
+        let target = rand::random::<f64>() * self.cdf[self.cdf.len() - 1];
+        let pixel = self.cdf.partition_point(|total| *total < target).min(self.cdf.len() - 1);
+        let x = (pixel % self.width) as f64 + rand::random::<f64>();
+        let y = (pixel / self.width) as f64 + rand::random::<f64>();
+        let size = self.width.max(self.height) as f64;
+        Point2::new(
+            (2.0 * x - self.width as f64) / size,
+            (self.height as f64 - 2.0 * y) / size,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn samples_stay_inside_the_opening() {
+        let hexagon = Aperture::polygon(6, 0.0);
+        let apothem = (f64::consts::PI / 6.0).cos();
+        for _ in 0..1000 {
+            let point = hexagon.sample();
+            let angle = point.y.atan2(point.x).rem_euclid(f64::consts::PI / 3.0) - f64::consts::PI / 6.0;
+            assert!(point.coords.norm() * angle.cos() <= apothem + 1e-9);
+        }
+
+        // Only the right hand column of the image is open.
+        let slit = Aperture::Image(Rc::new(BokehImage::new(2, 2, &[0.0, 1.0, 0.0, 1.0]).unwrap()));
+        for _ in 0..1000 {
+            assert!(slit.sample().x >= 0.0);
+        }
+        assert!(BokehImage::new(2, 2, &[0.0; 4]).is_none());
+
+        let plain = BokehImage::from_pgm(b"P2\n# a slit\n2 2\n255\n0 255\n0 255\n").unwrap();
+        let mut raw = b"P5 2 2 255\n".to_vec();
+        raw.extend_from_slice(&[0, 255, 0, 255]);
+        let raw = BokehImage::from_pgm(&raw).unwrap();
+        for image in &[plain, raw] {
+            assert_eq!(image.cdf, vec![0.0, 1.0, 1.0, 2.0]);
+        }
+        assert!(BokehImage::from_pgm(b"P5 2 2 255\n\0\xff").is_none());
+        assert!(BokehImage::from_pgm(b"P2 99999999999 99999999999 255\n1").is_none());
+    }
+}
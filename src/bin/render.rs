@@ -0,0 +1,59 @@
+// Renders a scene file natively, one numbered image for each frame:
+//
+//   render <scene> <directory> <first frame> <last frame> <frame rate> <samples> [<width> <height> [<bounces>]]
+//
+// Files the scene refers to are found relative to the scene file.
+use canvas::scene_loader::parse_scene_with;
+use canvas::sequence::Sequence;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if let Err(error) = run(&args) {
+        eprintln!("{}", error);
+        process::exit(1);
+    }
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    if args.len() != 7 && args.len() != 9 && args.len() != 10 {
+        return Err(format!(
+            "usage: {} <scene> <directory> <first frame> <last frame> <frame rate> <samples> [<width> <height> [<bounces>]]",
+            args[0]
+        ));
+    }
+
+    // Frames, samples and sizes are whole numbers, and samples and sizes
+    // must be at least one.
+    let count = |index: usize, least: usize| match args[index].parse::<usize>() {
+        Ok(count) if count >= least => Ok(count),
+        _ => Err(format!("expected a whole number of at least {} but found '{}'", least, args[index])),
+    };
+    let text = fs::read_to_string(&args[1]).map_err(|error| format!("{}: {}", args[1], error))?;
+    let (first, last) = (count(3, 0)?, count(4, 0)?);
+    if last < first {
+        return Err(format!("the last frame {} comes before the first frame {}", last, first));
+    }
+    let end = last.checked_add(1).ok_or_else(|| format!("the last frame {} is too large", last))?;
+    let frame_rate = match args[5].parse::<f64>() {
+        Ok(rate) if rate > 0.0 && rate.is_finite() => rate,
+        _ => return Err(format!("expected a positive frame rate but found '{}'", args[5])),
+    };
+
+    let mut sequence = Sequence::new(first..end, frame_rate).with_samples(count(6, 1)?);
+    if args.len() >= 9 {
+        sequence = sequence.with_size(count(7, 1)?, count(8, 1)?);
+    }
+    if args.len() == 10 {
+        let bounces = args[9]
+            .parse::<u32>()
+            .map_err(|_| format!("expected a whole number of bounces but found '{}'", args[9]))?;
+        sequence = sequence.with_bounces(bounces);
+    }
+    let folder = Path::new(&args[1]).parent().unwrap_or_else(|| Path::new(""));
+    let read = |path: &str| fs::read(folder.join(path)).map_err(|error| format!("{}: {}", path, error));
+    sequence.render(|time| parse_scene_with(&text, time, &read), Path::new(&args[2]))
+}
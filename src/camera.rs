@@ -1,21 +1,167 @@
+use crate::aperture::Aperture;
 use crate::ray::Ray;
 use nalgebra::{Point3, Vector3};
-use rand;
 use std::f64;
 
-pub struct Camera {
-    position: Point3<f64>,
+// Turns points on the image into rays into the scene.
+pub trait Camera {
+    // A ray through a random point in the pixel at (x, y), or nothing for
+    // pixels which see nothing, such as those outside a fisheye's circle.
+    fn ray(&self, x: usize, y: usize, width: usize, height: usize) -> Option<Ray>;
+
+    // Brings a point in the scene into focus, giving the distance focused at,
+    // or nothing for cameras which can't focus or can't focus there.
+    fn focus_on(&mut self, _point: &Point3<f64>) -> Option<f64> {
+        None
+    }
+}
+
+// Lets cameras picked at run time, such as from a scene file, be used
+// wherever a camera is expected.
+impl Camera for Box<dyn Camera> {
+    fn ray(&self, x: usize, y: usize, width: usize, height: usize) -> Option<Ray> {
+        self.as_ref().ray(x, y, width, height)
+    }
+
+    fn focus_on(&mut self, point: &Point3<f64>) -> Option<f64> {
+        self.as_mut().focus_on(point)
+    }
+}
+
+// Where a camera stands and which way it faces. Camera space looks down
+// negative z with y up.
+#[derive(Copy, Clone)]
+pub struct View {
+    pub position: Point3<f64>,
+    right: Vector3<f64>,
+    up: Vector3<f64>,
+    back: Vector3<f64>,
+}
+
+impl View {
+    // Turns the view `vertical_angle` degrees up and then `horizontal_angle`
+    // degrees to the left, starting from looking down negative z.
+    pub fn new(position: Point3<f64>, horizontal_angle: f64, vertical_angle: f64) -> Self {
+        let rotated = |direction: Vector3<f64>| {
+            let tilted = angle_axis(&direction, vertical_angle, &Vector3::new(-1.0, 0.0, 0.0));
+            angle_axis(&tilted, horizontal_angle, &Vector3::new(0.0, -1.0, 0.0))
+        };
+        View {
+            position,
+            right: rotated(Vector3::new(1.0, 0.0, 0.0)),
+            up: rotated(Vector3::new(0.0, 1.0, 0.0)),
+            back: rotated(Vector3::new(0.0, 0.0, 1.0)),
+        }
+    }
+
+    // Looks from `eye` towards `target`, keeping `up` towards the top of the
+    // image. Looking straight along `up`, as in a plan view, the top of the
+    // image is turned towards negative z instead, or towards y when looking
+    // along z. The eye and target must differ.
+    pub fn look_at(eye: Point3<f64>, target: Point3<f64>, up: Vector3<f64>) -> Self {
+        let back = (eye - target).normalize();
+        let up = if up.cross(&back).norm() > 1e-9 {
+            up
+        } else if back.z.abs() < 0.9 {
+            Vector3::new(0.0, 0.0, -1.0)
+        } else {
+            Vector3::new(0.0, 1.0, 0.0)
+        };
+        let right = up.cross(&back).normalize();
+        View {
+            position: eye,
+            right,
+            up: back.cross(&right),
+            back,
+        }
+    }
+
+    // Turns the view about its direction by an angle in degrees.
+    pub fn with_roll(mut self, roll: f64) -> Self {
+        self.right = angle_axis(&self.right, roll, &self.back);
+        self.up = angle_axis(&self.up, roll, &self.back);
+        self
+    }
+
+    // The view from an eye `offset` to the right, turned in to look at the
+    // point `convergence` in front of this view.
+    pub fn eye(self, offset: f64, convergence: f64) -> Self {
+        let eye = self.position + self.right * offset;
+        let target = self.position - self.back * convergence;
+        let back = (eye - target).normalize();
+        let right = self.up.cross(&back).normalize();
+        View {
+            position: eye,
+            right,
+            up: back.cross(&right),
+            back,
+        }
+    }
+
+    // Somewhere between this view and another, with the directions blended
+    // and squared up again.
+    pub fn lerp(&self, other: &View, t: f64) -> Self {
+        let back = self.back.lerp(&other.back, t).normalize();
+        let right = self.up.lerp(&other.up, t).cross(&back).normalize();
+        View {
+            position: self.position + (other.position - self.position) * t,
+            right,
+            up: back.cross(&right),
+            back,
+        }
+    }
+
+    pub fn to_world(self, direction: &Vector3<f64>) -> Vector3<f64> {
+        self.right * direction.x + self.up * direction.y + self.back * direction.z
+    }
+
+    pub fn to_local(self, direction: &Vector3<f64>) -> Vector3<f64> {
+        Vector3::new(direction.dot(&self.right), direction.dot(&self.up), direction.dot(&self.back))
+    }
+
+    pub fn forward(&self) -> Vector3<f64> {
+        -self.back
+    }
+}
+
+// A random point in the pixel at (x, y), with the whole image running from
+// zero to one across and down.
+pub fn image_point(x: usize, y: usize, width: usize, height: usize) -> (f64, f64) {
+    (
+        (x as f64 + rand::random::<f64>()) / width as f64,
+        (y as f64 + rand::random::<f64>()) / height as f64,
+    )
+}
+
+// An alternative to sizing the sensor, in degrees across the whole image.
+#[derive(Copy, Clone)]
+pub enum FieldOfView {
+    Vertical(f64),
+    Horizontal(f64),
+}
+
+// A perspective camera with a thin lens, which blurs whatever is away from
+// the plane of focus.
+pub struct ThinLens {
+    view: View,
     sensor: f64,
-    focal_length: f64,
+    field_of_view: Option<FieldOfView>,
     object_distance: f64,
-    fstop: f64,
+    // The plane brought into focus, as a point on it and its normal. It
+    // faces straight down the lens unless the lens is tilted.
+    focal_plane: (Vector3<f64>, Vector3<f64>),
+    // How far the image is moved off the axis of the lens, in image heights
+    // to the right and up.
+    shift: (f64, f64),
+    // Angles in degrees the lens is tilted down and swung to the left by.
+    tilt: (f64, f64),
     aperture: f64,
+    shape: Aperture,
+    cat_eye: f64,
     image_distance: f64,
-    vertical_angle: f64,
-    horizontal_angle: f64,
 }
 
-impl Camera {
+impl ThinLens {
     pub fn new(
         position: Point3<f64>,
         sensor: f64,
@@ -25,65 +171,169 @@ impl Camera {
         horizontal_angle: f64,
         vertical_angle: f64,
     ) -> Self {
+        ThinLens::with_view(View::new(position, horizontal_angle, vertical_angle), sensor, focal_length, focus, fstop)
+    }
+
+    pub fn with_view(view: View, sensor: f64, focal_length: f64, focus: f64, fstop: f64) -> Self {
         Self {
-            position: position,
-            sensor: sensor,
-            focal_length: focal_length,
+            view,
+            sensor,
+            field_of_view: None,
             object_distance: -focus,
-            fstop: fstop,
+            focal_plane: (Vector3::new(0.0, 0.0, -focus), Vector3::new(0.0, 0.0, 1.0)),
+            shift: (0.0, 0.0),
+            tilt: (0.0, 0.0),
             aperture: focal_length / fstop,
+            shape: Aperture::Circle,
+            cat_eye: 0.0,
             image_distance: 1.0 / (1.0 / focal_length - 1.0 / -focus),
-            vertical_angle: vertical_angle,
-            horizontal_angle: horizontal_angle,
         }
     }
 
-    pub fn ray(&self, x: usize, y: usize, width: usize, height: usize) -> Ray {
-        let sensor_point = self.sensor_point(x, y, width, height);
-        let focus_point = self.focus_point(sensor_point);
-        let aperture_point = self.aperture_point();
+    // Sizes the sensor to take in the given angle, whatever the shape of the
+    // image.
+    pub fn with_field_of_view(mut self, field_of_view: FieldOfView) -> Self {
+        self.field_of_view = Some(field_of_view);
+        self
+    }
 
-        let direction = (focus_point - aperture_point).normalize();
-        Ray {
-            origin: self.position,
-            direction: self.rotated(direction),
-        }
+    pub fn with_aperture(mut self, shape: Aperture) -> Self {
+        self.shape = shape;
+        self
     }
 
-    fn rotated(&self, direction: Vector3<f64>) -> Vector3<f64> {
-        let x_axis = Vector3::new(-1.0, 0.0, 0.0);
-        let y_axis = Vector3::new(0.0, -1.0, 0.0);
-        let direction1 = angle_axis(&direction, self.vertical_angle, &x_axis);
-        angle_axis(&direction1, self.horizontal_angle, &y_axis)
+    // Clips the aperture towards the edges of the image, as the barrel of a
+    // real lens does, turning bokeh into cat's eyes and darkening corners.
+    // Zero leaves the aperture whole, while one has the corners of the
+    // image see half of it.
+    pub fn with_cat_eye(mut self, strength: f64) -> Self {
+        self.cat_eye = strength;
+        self
     }
 
-    fn focus_point(&self, sensor_point: Point3<f64>) -> Vector3<f64> {
-        let origin = Point3::new(0.0, 0.0, 0.0);
-        let sensor_to_lens = origin - sensor_point;
-        let lens_world_ray = Ray {
-            origin,
-            direction: sensor_to_lens.normalize(),
-        };
-        let focus_ratio = self.object_distance / lens_world_ray.direction.z;
-        lens_world_ray.direction * focus_ratio
+    // Moves the image across the sensor, as architectural cameras do to take
+    // in the top of a building while keeping its verticals parallel.
+    pub fn with_shift(mut self, right: f64, up: f64) -> Self {
+        self.shift = (right, up);
+        self
+    }
+
+    // Tilts the lens down and swings it to the left by angles in degrees,
+    // keeping the sensor where it is. By the Scheimpflug principle the plane
+    // of focus then meets the planes of the sensor and the lens in a line,
+    // so tilting lays it down towards the ground in front of the camera and
+    // swinging turns it to follow things receding to the right.
+    pub fn with_tilt(mut self, tilt: f64, swing: f64) -> Self {
+        self.tilt = (tilt, swing);
+        self.focal_plane = self.focal_plane();
+        self
+    }
+
+    fn focal_plane(&self) -> (Vector3<f64>, Vector3<f64>) {
+        let axis = Vector3::new(0.0, 0.0, 1.0);
+        let lens_normal = angle_axis(
+            &angle_axis(&axis, -self.tilt.0, &Vector3::new(1.0, 0.0, 0.0)),
+            self.tilt.1,
+            &Vector3::new(0.0, 1.0, 0.0),
+        );
+        let across = lens_normal.xy();
+        if across.norm() < 1e-9 {
+            return (Vector3::new(0.0, 0.0, self.object_distance), axis);
+        }
+
+        // The sensor's centre, measured across the tilted lens, comes into
+        // focus somewhere straight out along the axis. The focal length is
+        // the one which pairs the sensor with the focus distance.
+        let focal_length = 1.0 / (1.0 / self.image_distance - 1.0 / self.object_distance);
+        let image = self.image_distance * lens_normal.z;
+        let object = 1.0 / (1.0 / focal_length - 1.0 / image);
+        let centre = Vector3::new(0.0, 0.0, -object / lens_normal.z);
+
+        // Where the sensor and lens planes meet, and the direction of that line.
+        let offset = across * (-lens_normal.z * self.image_distance / across.norm_squared());
+        let hinge = Vector3::new(offset.x, offset.y, self.image_distance);
+        let along = lens_normal.cross(&axis);
+
+        (centre, along.cross(&(hinge - centre)).normalize())
+    }
+
+    // Where the ray through the centre of the lens from a point on the sensor
+    // meets the plane of focus, or nothing if it never does.
+    fn focus_point(&self, sensor_point: Point3<f64>) -> Option<Vector3<f64>> {
+        let direction = (Point3::origin() - sensor_point).normalize();
+        let (point, normal) = &self.focal_plane;
+        let distance = point.dot(normal) / direction.dot(normal);
+        if distance > 0.0 && distance.is_finite() {
+            Some(direction * distance)
+        } else {
+            None
+        }
     }
 
     fn sensor_point(&self, x: usize, y: usize, width: usize, height: usize) -> Point3<f64> {
         let aspect = width as f64 / height as f64;
-        let vx = ((x as f64 + rand::random::<f64>()) / width as f64 - 0.5) * aspect;
-        let vy = (y as f64 + rand::random::<f64>()) / height as f64 - 0.5;
-        let sensor_x = -vx * self.sensor;
-        let sensor_y = vy * self.sensor;
+        let (u, v) = image_point(x, y, width, height);
+        let vx = (u - 0.5) * aspect + self.shift.0;
+        let vy = v - 0.5 - self.shift.1;
+        let sensor = self.sensor_height(aspect);
+        let sensor_x = -vx * sensor;
+        let sensor_y = vy * sensor;
         Point3::new(sensor_x, sensor_y, self.image_distance)
     }
 
-    fn aperture_point(&self) -> Vector3<f64> {
-        let r_max = self.aperture / 2.0;
-        let r = (rand::random::<f64>() * r_max * r_max).sqrt();
-        let angle = rand::random::<f64>() * f64::consts::PI * 2.0;
-        let x = r * angle.cos();
-        let y = r * angle.sin();
-        Vector3::new(x, y, 0.0)
+    fn sensor_height(&self, aspect: f64) -> f64 {
+        let span = |degrees: f64| 2.0 * self.image_distance * (degrees.to_radians() * 0.5).tan();
+        match self.field_of_view {
+            None => self.sensor,
+            Some(FieldOfView::Vertical(degrees)) => span(degrees),
+            Some(FieldOfView::Horizontal(degrees)) => span(degrees) / aspect,
+        }
+    }
+
+    // A point on the aperture, or nothing if the barrel of the lens blocks
+    // it for this point on the sensor.
+    fn aperture_point(&self, sensor_point: &Point3<f64>, sensor: f64) -> Option<Vector3<f64>> {
+        let point = self.shape.sample();
+        if self.cat_eye > 0.0 {
+            let corner = sensor * 0.5 * f64::consts::SQRT_2;
+            let barrel = Vector3::new(sensor_point.x, sensor_point.y, 0.0) * (self.cat_eye / corner);
+            if (Vector3::new(point.x, point.y, 0.0) + barrel).norm() > 1.0 {
+                return None;
+            }
+        }
+        Some(Vector3::new(point.x, point.y, 0.0) * (self.aperture / 2.0))
+    }
+}
+
+impl Camera for ThinLens {
+    // Focuses at the depth of the point along the view, leaving the framing
+    // as it is.
+    fn focus_on(&mut self, point: &Point3<f64>) -> Option<f64> {
+        let depth = self.view.to_local(&(point - self.view.position)).z;
+        if depth >= 0.0 {
+            return None;
+        }
+        self.object_distance = depth;
+        self.focal_plane = self.focal_plane();
+        Some(-depth)
+    }
+
+    fn ray(&self, x: usize, y: usize, width: usize, height: usize) -> Option<Ray> {
+        let sensor_point = self.sensor_point(x, y, width, height);
+        let sensor = self.sensor_height(width as f64 / height as f64);
+        let aperture_point = self.aperture_point(&sensor_point, sensor)?;
+
+        // Points on a plane of focus tilted away from the sensor's line of
+        // sight are at infinity, where every ray from the lens runs parallel.
+        let direction = match self.focus_point(sensor_point) {
+            Some(focus_point) => (focus_point - aperture_point).normalize(),
+            None => (Point3::origin() - sensor_point).normalize(),
+        };
+        Some(Ray {
+            origin: self.view.position,
+            direction: self.view.to_world(&direction),
+            time: 0.0,
+        })
     }
 }
 
@@ -95,3 +345,53 @@ fn angle_axis(direction: &Vector3<f64>, angle: f64, axis: &Vector3<f64>) -> Vect
     let third = k * (k.dot(direction)) * (1.0 - theta.cos());
     first + second + third
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn look_at_aims_the_centre_of_the_image_at_the_target() {
+        let view = View::look_at(
+            Point3::new(3.0, 2.0, 5.0),
+            Point3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+        )
+        .with_roll(30.0);
+        let camera = ThinLens::with_view(view, 0.024, 0.040, 10.0, 1000.0)
+            .with_field_of_view(FieldOfView::Vertical(1e-3));
+
+        let ray = camera.ray(50, 50, 101, 101).unwrap();
+        let expected = (Point3::origin() - Point3::new(3.0, 2.0, 5.0)).normalize();
+        assert!((ray.direction - expected).norm() < 1e-4);
+        assert!(view.right.dot(&view.up).abs() < 1e-9);
+
+        // Straight down, with the top of the image towards negative z.
+        let plan = View::look_at(Point3::new(0.0, 10.0, 0.0), Point3::origin(), Vector3::new(0.0, 1.0, 0.0));
+        assert_eq!(plan.forward(), Vector3::new(0.0, -1.0, 0.0));
+        assert_eq!(plan.right, Vector3::new(1.0, 0.0, 0.0));
+        assert_eq!(plan.up, Vector3::new(0.0, 0.0, -1.0));
+        let along = View::look_at(Point3::new(0.0, 0.0, 5.0), Point3::origin(), Vector3::new(0.0, 0.0, 1.0));
+        assert_eq!(along.up, Vector3::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn tilted_planes_of_focus_pass_through_the_hinge_line() {
+        let view = View::new(Point3::origin(), 0.0, 0.0);
+        let tilt = 4.0f64;
+        let camera = ThinLens::with_view(view, 0.024, 0.05, 5.0, 2.0).with_tilt(tilt, 0.0);
+
+        // Merklinger's hinge rule puts the plane of focus through a line
+        // under the lens, the focal length over the sine of the tilt away.
+        let focal_length = 1.0 / (1.0 / camera.image_distance + 1.0 / 5.0);
+        let hinge = Vector3::new(0.0, -focal_length / tilt.to_radians().sin(), 0.0);
+        let (point, normal) = camera.focal_plane;
+        assert!((hinge - point).dot(&normal).abs() < 1e-9);
+
+        let sensor = |y: f64| Point3::new(0.0, y, camera.image_distance);
+        let below = camera.focus_point(sensor(0.006)).unwrap();
+        let ahead = camera.focus_point(sensor(0.0)).unwrap();
+        assert!(below.y < 0.0 && below.z > ahead.z);
+        assert!(camera.focus_point(sensor(-0.01)).is_none());
+    }
+}
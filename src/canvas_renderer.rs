@@ -2,7 +2,7 @@ use std::cell::RefCell;
 use std::rc::Rc;
 
 use wasm_bindgen::{prelude::*, Clamped, JsCast};
-use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, ImageData};
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, ImageData, MouseEvent};
 
 use crate::tracer::Tracer;
 
@@ -32,7 +32,7 @@ impl CanvasRenderer {
         }
     }
 
-    pub fn start(&self, mut tracer: Tracer) {
+    pub fn start(&self, tracer: Tracer) {
         let context = self
             .canvas
             .get_context("2d")
@@ -42,11 +42,13 @@ impl CanvasRenderer {
             .unwrap();
 
         let mut data = vec![0u8; self.width * self.height * 4];
+        let tracer = Rc::new(RefCell::new(tracer));
+        self.focus_on_click(tracer.clone());
 
         let f = Rc::new(RefCell::new(None));
         let g = f.clone();
         let image_data = ImageData::new_with_u8_clamped_array_and_sh(
-            Clamped(&mut data),
+            Clamped(&data),
             self.width as u32,
             self.height as u32,
         )
@@ -63,7 +65,7 @@ impl CanvasRenderer {
             let end = start + tick_ms;
 
             loop {
-                tracer.update(&mut data);
+                tracer.borrow_mut().update(&mut data);
                 if performance.now() > end {
                     break;
                 }
@@ -73,11 +75,31 @@ impl CanvasRenderer {
                 .expect("should have a value");
 
             request_animation_frame(f.borrow().as_ref().unwrap());
-        }) as Box<FnMut()>));
+        }) as Box<dyn FnMut()>));
 
         request_animation_frame(g.borrow().as_ref().unwrap());
     }
 
+    // Clicking the canvas refocuses the camera on whatever is under the
+    // pointer and starts the image over.
+    fn focus_on_click(&self, tracer: Rc<RefCell<Tracer>>) {
+        let canvas = self.canvas.clone();
+        let (width, height) = (self.width, self.height);
+        let click = Closure::wrap(Box::new(move |event: MouseEvent| {
+            // The canvas may be shown at a different size to the image.
+            let x = f64::from(event.offset_x()) * width as f64 / f64::from(canvas.client_width());
+            let y = f64::from(event.offset_y()) * height as f64 / f64::from(canvas.client_height());
+            if x >= 0.0 && y >= 0.0 && (x as usize) < width && (y as usize) < height {
+                tracer.borrow_mut().focus_at(x as usize, y as usize);
+            }
+        }) as Box<dyn FnMut(MouseEvent)>);
+
+        self.canvas
+            .add_event_listener_with_callback("click", click.as_ref().unchecked_ref())
+            .expect("should register the click listener");
+        click.forget();
+    }
+
     pub fn width(&self) -> usize {
         self.width
     }
@@ -91,7 +113,7 @@ fn window() -> web_sys::Window {
     web_sys::window().expect("no global `window` exists")
 }
 
-fn request_animation_frame(f: &Closure<FnMut()>) {
+fn request_animation_frame(f: &Closure<dyn FnMut()>) {
     window()
         .request_animation_frame(f.as_ref().unchecked_ref())
         .expect("should register `requestAnimationFrame` OK");
@@ -0,0 +1,72 @@
+use nalgebra::Vector3;
+
+// Measured complex refractive indices, n + ik, at the red, green and blue
+// wavelengths of 650nm, 550nm and 450nm.
+const METALS: [(&str, [f64; 3], [f64; 3]); 6] = [
+    ("gold", [0.143, 0.374, 1.442], [3.983, 2.385, 1.603]),
+    ("silver", [0.155, 0.117, 0.138], [4.828, 3.122, 2.147]),
+    ("copper", [0.200, 0.924, 1.102], [3.912, 2.452, 2.142]),
+    ("aluminium", [1.657, 0.880, 0.521], [9.224, 6.270, 4.837]),
+    ("chrome", [3.180, 3.120, 2.440], [3.300, 3.320, 3.180]),
+    ("titanium", [2.740, 2.540, 2.270], [3.810, 3.430, 3.040]),
+];
+
+// A metal's complex refractive index in each channel.
+#[derive(Copy, Clone)]
+pub struct Conductor {
+    eta: Vector3<f64>,
+    k: Vector3<f64>,
+}
+
+impl Conductor {
+    pub fn new(eta: Vector3<f64>, k: Vector3<f64>) -> Self {
+        Conductor { eta, k }
+    }
+
+    pub fn named(name: &str) -> Option<Self> {
+        METALS
+            .iter()
+            .find(|(metal, _, _)| *metal == name)
+            .map(|(_, eta, k)| Conductor::new(Vector3::from(*eta), Vector3::from(*k)))
+    }
+
+    // Exact Fresnel reflectance of the metal for unpolarised light.
+    pub fn reflectance(&self, cos_incident: f64) -> Vector3<f64> {
+        let cos2 = cos_incident * cos_incident;
+        let sin2 = 1.0 - cos2;
+
+        Vector3::from_fn(|channel, _| {
+            let eta2 = self.eta[channel] * self.eta[channel];
+            let k2 = self.k[channel] * self.k[channel];
+
+            let t0 = eta2 - k2 - sin2;
+            let a2_plus_b2 = (t0 * t0 + 4.0 * eta2 * k2).sqrt();
+            let t1 = a2_plus_b2 + cos2;
+            let a = (0.5 * (a2_plus_b2 + t0)).max(0.0).sqrt();
+            let t2 = 2.0 * cos_incident * a;
+            let s = (t1 - t2) / (t1 + t2);
+
+            let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+            let t4 = t2 * sin2;
+            let p = s * (t3 - t4) / (t3 + t4);
+
+            0.5 * (s + p)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn gold_reflects_red_more_than_blue() {
+        let gold = Conductor::named("gold").unwrap();
+        let head_on = gold.reflectance(1.0);
+        assert!(head_on.x > 0.9 && head_on.z < 0.5);
+
+        let grazing = gold.reflectance(0.0);
+        assert!((grazing - Vector3::new(1.0, 1.0, 1.0)).amax() < 1e-9);
+        assert!(Conductor::named("unobtainium").is_none());
+    }
+}
@@ -0,0 +1,181 @@
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::shape::{convex_span, quadratic_roots, Frame, Shape, Span, Surface, SurfaceSample, BIAS};
+use nalgebra::{Point2, Point3, Vector3};
+use std::f64;
+
+pub struct Cone {
+    base: Point3<f64>,
+    frame: Frame,
+    radius: f64,
+    height: f64,
+    material: Material,
+}
+
+impl Cone {
+    pub fn new(
+        base: Point3<f64>,
+        axis: Vector3<f64>,
+        radius: f64,
+        height: f64,
+        material: Material,
+    ) -> Self {
+        Cone {
+            base,
+            frame: Frame::new(base, axis),
+            radius,
+            height,
+            material,
+        }
+    }
+
+    fn slope(&self) -> f64 {
+        self.radius / self.height
+    }
+
+    fn side_distances(&self, ray: &Ray) -> Vec<f64> {
+        let (o, d) = (ray.origin, ray.direction);
+        let k = self.slope();
+        let rim = self.radius - k * o.y;
+        let a = d.x * d.x + d.z * d.z - k * k * d.y * d.y;
+        let b = 2.0 * (o.x * d.x + o.z * d.z + k * d.y * rim);
+        let c = o.x * o.x + o.z * o.z - rim * rim;
+        quadratic_roots(a, b, c)
+            .into_iter()
+            .filter(|t| {
+                let y = o.y + d.y * t;
+                y >= 0.0 && y <= self.height
+            })
+            .collect()
+    }
+
+    fn cap_distance(&self, ray: &Ray) -> Option<f64> {
+        if ray.direction.y.abs() < f64::EPSILON {
+            return None;
+        }
+
+        let t = -ray.origin.y / ray.direction.y;
+        let x = ray.origin.x + ray.direction.x * t;
+        let z = ray.origin.z + ray.direction.z * t;
+        if x * x + z * z <= self.radius * self.radius {
+            Some(t)
+        } else {
+            None
+        }
+    }
+}
+
+impl Shape for Cone {
+    fn intersection_distance(&self, ray: &Ray) -> f64 {
+        let local = self.frame.ray_to_local(ray);
+        self.side_distances(&local)
+            .into_iter()
+            .chain(self.cap_distance(&local))
+            .filter(|t| *t > BIAS)
+            .fold(f64::INFINITY, f64::min)
+    }
+
+    fn surface(&self, ray: &Ray, distance: f64) -> Surface {
+        let point = self.frame.point_to_local(&(ray.origin + ray.direction * distance));
+        let r = (point.x * point.x + point.z * point.z).sqrt();
+        let phi = (point.z.atan2(point.x) + f64::consts::PI) / (2.0 * f64::consts::PI);
+
+        let k = self.slope();
+        let side_error = (r - (self.radius - k * point.y)).abs();
+        if side_error < point.y.abs() {
+            let normal = Vector3::new(point.x, k * (self.radius - k * point.y), point.z);
+            let normal = self.frame.vector_to_world(&normal.normalize());
+            Surface {
+                normal,
+                uv: Point2::new(phi, point.y / self.height),
+                front_face: normal.dot(&ray.direction) < 0.0,
+            }
+        } else {
+            let normal = self.frame.vector_to_world(&Vector3::new(0.0, -1.0, 0.0));
+            Surface {
+                normal,
+                uv: Point2::new(phi, r / self.radius),
+                front_face: normal.dot(&ray.direction) < 0.0,
+            }
+        }
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn area(&self) -> f64 {
+        let slant = (self.radius * self.radius + self.height * self.height).sqrt();
+        f64::consts::PI * self.radius * (self.radius + slant)
+    }
+
+    fn sample_surface(&self) -> Option<SurfaceSample> {
+        let slant = (self.radius * self.radius + self.height * self.height).sqrt();
+        let side = slant / (self.radius + slant);
+        let phi = 2.0 * f64::consts::PI * rand::random::<f64>();
+        let (local_point, local_normal) = if rand::random::<f64>() < side {
+            // Distance from the apex grows with the square root to stay uniform.
+            let s = rand::random::<f64>().sqrt();
+            let normal = Vector3::new(phi.cos() * self.height, self.radius, phi.sin() * self.height);
+            (
+                Vector3::new(self.radius * s * phi.cos(), self.height * (1.0 - s), self.radius * s * phi.sin()),
+                normal / slant,
+            )
+        } else {
+            let r = self.radius * rand::random::<f64>().sqrt();
+            (
+                Vector3::new(r * phi.cos(), 0.0, r * phi.sin()),
+                Vector3::new(0.0, -1.0, 0.0),
+            )
+        };
+
+        Some(SurfaceSample {
+            point: self.base + self.frame.vector_to_world(&local_point),
+            normal: self.frame.vector_to_world(&local_normal),
+        })
+    }
+
+    fn spans(&self, ray: &Ray) -> Vec<Span> {
+        let local = self.frame.ray_to_local(ray);
+        convex_span(self.side_distances(&local).into_iter().chain(self.cap_distance(&local)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn normals_lean_up_the_slope_and_uvs_run_to_the_apex() {
+        let material = Material::new(
+            Vector3::new(1.0, 1.0, 1.0),
+            1.0,
+            0.0,
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.04, 0.04, 0.04),
+            0.0,
+            0.0,
+        );
+        let cone = Cone::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0), 1.0, 2.0, material);
+        let ray = |origin: Point3<f64>, direction: Vector3<f64>| Ray {
+            origin,
+            direction,
+            time: 0.0,
+        };
+
+        // Half way up, the side is half as far out as the rim.
+        let side = ray(Point3::new(-5.0, 1.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
+        assert!((cone.intersection_distance(&side) - 4.5).abs() < 1e-9);
+        let surface = cone.surface(&side, 4.5);
+        assert!((surface.normal - Vector3::new(-2.0, 1.0, 0.0) / 5f64.sqrt()).norm() < 1e-9);
+        assert!(surface.front_face);
+        assert!((surface.uv.y - 0.5).abs() < 1e-9);
+
+        let base = ray(Point3::new(0.0, -5.0, 0.25), Vector3::new(0.0, 1.0, 0.0));
+        assert!((cone.intersection_distance(&base) - 5.0).abs() < 1e-9);
+        let surface = cone.surface(&base, 5.0);
+        assert!((surface.normal - Vector3::new(0.0, -1.0, 0.0)).norm() < 1e-9);
+        assert!(surface.front_face);
+        assert!((surface.uv.y - 0.25).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,200 @@
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::shape::{Shape, Span, Surface, BIAS};
+use std::f64;
+
+#[derive(Copy, Clone)]
+pub enum Operation {
+    Union,
+    Intersection,
+    Difference,
+}
+
+impl Operation {
+    fn contains(self, left: bool, right: bool) -> bool {
+        match self {
+            Operation::Union => left || right,
+            Operation::Intersection => left && right,
+            Operation::Difference => left && !right,
+        }
+    }
+}
+
+pub struct Csg {
+    operation: Operation,
+    left: Box<dyn Shape>,
+    right: Box<dyn Shape>,
+    material: Material,
+}
+
+impl Csg {
+    pub fn new(
+        operation: Operation,
+        left: Box<dyn Shape>,
+        right: Box<dyn Shape>,
+        material: Material,
+    ) -> Self {
+        Csg {
+            operation,
+            left,
+            right,
+            material,
+        }
+    }
+
+    pub fn union(left: Box<dyn Shape>, right: Box<dyn Shape>, material: Material) -> Self {
+        Csg::new(Operation::Union, left, right, material)
+    }
+
+    pub fn intersection(left: Box<dyn Shape>, right: Box<dyn Shape>, material: Material) -> Self {
+        Csg::new(Operation::Intersection, left, right, material)
+    }
+
+    pub fn difference(left: Box<dyn Shape>, right: Box<dyn Shape>, material: Material) -> Self {
+        Csg::new(Operation::Difference, left, right, material)
+    }
+}
+
+impl Shape for Csg {
+    fn intersection_distance(&self, ray: &Ray) -> f64 {
+        boundaries(&self.spans(ray))
+            .find(|(t, _)| *t > BIAS)
+            .map_or(f64::INFINITY, |(t, _)| t)
+    }
+
+    fn surface(&self, ray: &Ray, distance: f64) -> Surface {
+        let entering = nearest(boundaries(&self.spans(ray)), distance)
+            .map(|(_, entering)| entering)
+            .unwrap_or(true);
+
+        let left = nearest(boundaries(&self.left.spans(ray)), distance);
+        let right = nearest(boundaries(&self.right.spans(ray)), distance);
+        let owner = match (left, right) {
+            (Some((l, _)), Some((r, _))) if (r - distance).abs() < (l - distance).abs() => &self.right,
+            (None, Some(_)) => &self.right,
+            _ => &self.left,
+        };
+
+        // Children may face their normals either way, so orient them by
+        // whether the ray is entering or leaving the combined solid.
+        let surface = owner.surface(ray, distance);
+        let outward = (surface.normal.dot(&ray.direction) < 0.0) == entering;
+        Surface {
+            normal: if outward { surface.normal } else { -surface.normal },
+            uv: surface.uv,
+            front_face: entering,
+        }
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn spans(&self, ray: &Ray) -> Vec<Span> {
+        combine(
+            self.operation,
+            &self.left.spans(ray),
+            &self.right.spans(ray),
+        )
+    }
+}
+
+// Every boundary along a ray, paired with whether the ray enters there.
+fn boundaries(spans: &[Span]) -> impl Iterator<Item = (f64, bool)> + '_ {
+    spans
+        .iter()
+        .flat_map(|span| vec![(span.enter, true), (span.exit, false)])
+        .filter(|(t, _)| t.is_finite())
+}
+
+fn nearest<I: Iterator<Item = (f64, bool)>>(boundaries: I, distance: f64) -> Option<(f64, bool)> {
+    boundaries.fold(None, |closest: Option<(f64, bool)>, boundary| match closest {
+        Some(c) if (c.0 - distance).abs() <= (boundary.0 - distance).abs() => Some(c),
+        _ => Some(boundary),
+    })
+}
+
+fn combine(operation: Operation, left: &[Span], right: &[Span]) -> Vec<Span> {
+    // Spans from degenerate hits can be NaN, and are dropped whole so that
+    // every entry still has its exit.
+    let valid = |span: &&Span| !span.enter.is_nan() && !span.exit.is_nan();
+    let mut events: Vec<(f64, bool, bool)> = left
+        .iter()
+        .filter(valid)
+        .flat_map(|span| vec![(span.enter, true, true), (span.exit, true, false)])
+        .chain(
+            right
+                .iter()
+                .filter(valid)
+                .flat_map(|span| vec![(span.enter, false, true), (span.exit, false, false)]),
+        )
+        .collect();
+    events.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut spans = vec![];
+    let (mut in_left, mut in_right) = (false, false);
+    let mut enter = None;
+    for (t, is_left, entering) in events {
+        if is_left {
+            in_left = entering;
+        } else {
+            in_right = entering;
+        }
+
+        match (enter, operation.contains(in_left, in_right)) {
+            (None, true) => enter = Some(t),
+            (Some(start), false) => {
+                if start < t {
+                    spans.push(Span { enter: start, exit: t });
+                }
+                enter = None;
+            }
+            _ => {}
+        }
+    }
+    spans
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::sphere::Sphere;
+    use nalgebra::{Point3, Vector3};
+
+    #[test]
+    fn difference_carves_a_hollow_shell() {
+        let glass = Material::new(
+            Vector3::new(0.0, 0.0, 0.0),
+            1.6,
+            1.0,
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.04, 0.04, 0.04),
+            0.0,
+            0.0,
+        );
+        let shell = Csg::difference(
+            Box::new(Sphere::new(Point3::new(0.0, 0.0, 0.0), 2.0, glass.clone())),
+            Box::new(Sphere::new(Point3::new(0.0, 0.0, 0.0), 1.0, glass.clone())),
+            glass,
+        );
+        let ray = Ray {
+            origin: Point3::new(-5.0, 0.0, 0.0),
+            direction: Vector3::new(1.0, 0.0, 0.0),
+            time: 0.0,
+        };
+
+        assert_eq!(
+            shell.spans(&ray),
+            vec![Span { enter: 3.0, exit: 4.0 }, Span { enter: 6.0, exit: 7.0 }]
+        );
+
+        let inner = Ray {
+            origin: Point3::new(0.0, 0.0, 0.0),
+            direction: Vector3::new(1.0, 0.0, 0.0),
+            time: 0.0,
+        };
+        let distance = shell.intersection_distance(&inner);
+        assert_eq!(distance, 1.0);
+        assert_eq!(shell.surface(&inner, distance).normal, Vector3::new(-1.0, 0.0, 0.0));
+    }
+}
@@ -0,0 +1,148 @@
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::shape::{Shape, Span, Surface, SurfaceSample, BIAS};
+use nalgebra::{Point2, Point3, Vector3};
+use std::f64;
+
+pub struct Cuboid {
+    min: Point3<f64>,
+    max: Point3<f64>,
+    material: Material,
+}
+
+impl Cuboid {
+    pub fn new(min: Point3<f64>, max: Point3<f64>, material: Material) -> Self {
+        Cuboid { min, max, material }
+    }
+
+    fn slabs(&self, ray: &Ray) -> Option<(f64, f64)> {
+        let mut near = f64::NEG_INFINITY;
+        let mut far = f64::INFINITY;
+        for axis in 0..3 {
+            let inverse = 1.0 / ray.direction[axis];
+            let mut t0 = (self.min[axis] - ray.origin[axis]) * inverse;
+            let mut t1 = (self.max[axis] - ray.origin[axis]) * inverse;
+            if t0 > t1 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            near = near.max(t0);
+            far = far.min(t1);
+        }
+
+        if near > far {
+            None
+        } else {
+            Some((near, far))
+        }
+    }
+}
+
+impl Shape for Cuboid {
+    fn intersection_distance(&self, ray: &Ray) -> f64 {
+        match self.slabs(ray) {
+            Some((near, _)) if near > BIAS => near,
+            Some((_, far)) if far > BIAS => far,
+            _ => f64::INFINITY,
+        }
+    }
+
+    fn surface(&self, ray: &Ray, distance: f64) -> Surface {
+        let point = ray.origin + ray.direction * distance;
+        let center = nalgebra::center(&self.min, &self.max);
+        let half = (self.max - self.min) * 0.5;
+        let local = (point - center).component_div(&half);
+
+        let axis = local.iamax();
+        let mut normal = Vector3::zeros();
+        normal[axis] = local[axis].signum();
+
+        let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+        Surface {
+            normal,
+            uv: Point2::new((local[u] + 1.0) * 0.5, (local[v] + 1.0) * 0.5),
+            front_face: normal.dot(&ray.direction) < 0.0,
+        }
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn area(&self) -> f64 {
+        let size = self.max - self.min;
+        2.0 * (size.x * size.y + size.y * size.z + size.z * size.x)
+    }
+
+    fn sample_surface(&self) -> Option<SurfaceSample> {
+        let size = self.max - self.min;
+        let faces = [size.y * size.z, size.z * size.x, size.x * size.y];
+        let mut choice = rand::random::<f64>() * (faces[0] + faces[1] + faces[2]);
+        let mut axis = 0;
+        while axis < 2 && choice > faces[axis] {
+            choice -= faces[axis];
+            axis += 1;
+        }
+
+        let mut point = self.min + size.component_mul(&Vector3::new(
+            rand::random::<f64>(),
+            rand::random::<f64>(),
+            rand::random::<f64>(),
+        ));
+        let mut normal = Vector3::zeros();
+        if rand::random::<bool>() {
+            point[axis] = self.max[axis];
+            normal[axis] = 1.0;
+        } else {
+            point[axis] = self.min[axis];
+            normal[axis] = -1.0;
+        }
+
+        Some(SurfaceSample { point, normal })
+    }
+
+    fn spans(&self, ray: &Ray) -> Vec<Span> {
+        self.slabs(ray)
+            .map(|(enter, exit)| Span { enter, exit })
+            .into_iter()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn normals_point_out_of_the_hit_face_and_uvs_span_it() {
+        let material = Material::new(
+            Vector3::new(1.0, 1.0, 1.0),
+            1.0,
+            0.0,
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.04, 0.04, 0.04),
+            0.0,
+            0.0,
+        );
+        let cuboid = Cuboid::new(Point3::new(0.0, 0.0, 0.0), Point3::new(2.0, 4.0, 6.0), material);
+        let ray = |origin: Point3<f64>, direction: Vector3<f64>| Ray {
+            origin,
+            direction,
+            time: 0.0,
+        };
+
+        let front = ray(Point3::new(0.5, 3.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+        assert!((cuboid.intersection_distance(&front) - 5.0).abs() < 1e-9);
+        let surface = cuboid.surface(&front, 5.0);
+        assert_eq!(surface.normal, Vector3::new(0.0, 0.0, -1.0));
+        assert!(surface.front_face);
+        assert!((surface.uv - Point2::new(0.25, 0.75)).norm() < 1e-9);
+
+        // From inside, the far face is hit on its back.
+        let inside = ray(Point3::new(1.0, 1.0, 4.5), Vector3::new(1.0, 0.0, 0.0));
+        assert!((cuboid.intersection_distance(&inside) - 1.0).abs() < 1e-9);
+        let surface = cuboid.surface(&inside, 1.0);
+        assert_eq!(surface.normal, Vector3::new(1.0, 0.0, 0.0));
+        assert!(!surface.front_face);
+        assert!((surface.uv - Point2::new(0.25, 0.75)).norm() < 1e-9);
+    }
+}
@@ -0,0 +1,181 @@
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::shape::{convex_span, quadratic_roots, Frame, Shape, Span, Surface, SurfaceSample, BIAS};
+use nalgebra::{Point2, Point3, Vector3};
+use std::f64;
+
+pub struct Cylinder {
+    base: Point3<f64>,
+    frame: Frame,
+    radius: f64,
+    height: f64,
+    material: Material,
+}
+
+impl Cylinder {
+    pub fn new(
+        base: Point3<f64>,
+        axis: Vector3<f64>,
+        radius: f64,
+        height: f64,
+        material: Material,
+    ) -> Self {
+        Cylinder {
+            base,
+            frame: Frame::new(base, axis),
+            radius,
+            height,
+            material,
+        }
+    }
+
+    fn side_distances(&self, ray: &Ray) -> Vec<f64> {
+        let (o, d) = (ray.origin, ray.direction);
+        let a = d.x * d.x + d.z * d.z;
+        let b = 2.0 * (o.x * d.x + o.z * d.z);
+        let c = o.x * o.x + o.z * o.z - self.radius * self.radius;
+        quadratic_roots(a, b, c)
+            .into_iter()
+            .filter(|t| {
+                let y = o.y + d.y * t;
+                y >= 0.0 && y <= self.height
+            })
+            .collect()
+    }
+
+    fn cap_distances(&self, ray: &Ray) -> Vec<f64> {
+        if ray.direction.y.abs() < f64::EPSILON {
+            return vec![];
+        }
+
+        [0.0, self.height]
+            .iter()
+            .map(|y| (y - ray.origin.y) / ray.direction.y)
+            .filter(|t| {
+                let x = ray.origin.x + ray.direction.x * t;
+                let z = ray.origin.z + ray.direction.z * t;
+                x * x + z * z <= self.radius * self.radius
+            })
+            .collect()
+    }
+}
+
+impl Shape for Cylinder {
+    fn intersection_distance(&self, ray: &Ray) -> f64 {
+        let local = self.frame.ray_to_local(ray);
+        self.side_distances(&local)
+            .into_iter()
+            .chain(self.cap_distances(&local))
+            .filter(|t| *t > BIAS)
+            .fold(f64::INFINITY, f64::min)
+    }
+
+    fn surface(&self, ray: &Ray, distance: f64) -> Surface {
+        let point = self.frame.point_to_local(&(ray.origin + ray.direction * distance));
+        let r = (point.x * point.x + point.z * point.z).sqrt();
+        let phi = (point.z.atan2(point.x) + f64::consts::PI) / (2.0 * f64::consts::PI);
+
+        let side_error = (r - self.radius).abs();
+        if side_error < point.y.abs() && side_error < (point.y - self.height).abs() {
+            let normal = self.frame.vector_to_world(&(Vector3::new(point.x, 0.0, point.z) / r));
+            Surface {
+                normal,
+                uv: Point2::new(phi, point.y / self.height),
+                front_face: normal.dot(&ray.direction) < 0.0,
+            }
+        } else {
+            let up = if point.y * 2.0 > self.height { 1.0 } else { -1.0 };
+            let normal = self.frame.vector_to_world(&Vector3::new(0.0, up, 0.0));
+            Surface {
+                normal,
+                uv: Point2::new(phi, r / self.radius),
+                front_face: normal.dot(&ray.direction) < 0.0,
+            }
+        }
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn area(&self) -> f64 {
+        2.0 * f64::consts::PI * self.radius * (self.radius + self.height)
+    }
+
+    fn sample_surface(&self) -> Option<SurfaceSample> {
+        let side = self.height / (self.radius + self.height);
+        let phi = 2.0 * f64::consts::PI * rand::random::<f64>();
+        let (local_point, local_normal) = if rand::random::<f64>() < side {
+            let normal = Vector3::new(phi.cos(), 0.0, phi.sin());
+            let y = self.height * rand::random::<f64>();
+            (normal * self.radius + Vector3::new(0.0, y, 0.0), normal)
+        } else {
+            let r = self.radius * rand::random::<f64>().sqrt();
+            let (y, up) = if rand::random::<bool>() {
+                (self.height, 1.0)
+            } else {
+                (0.0, -1.0)
+            };
+            (
+                Vector3::new(r * phi.cos(), y, r * phi.sin()),
+                Vector3::new(0.0, up, 0.0),
+            )
+        };
+
+        Some(SurfaceSample {
+            point: self.base + self.frame.vector_to_world(&local_point),
+            normal: self.frame.vector_to_world(&local_normal),
+        })
+    }
+
+    fn spans(&self, ray: &Ray) -> Vec<Span> {
+        let local = self.frame.ray_to_local(ray);
+        convex_span(self.side_distances(&local).into_iter().chain(self.cap_distances(&local)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn normals_point_out_of_the_side_and_caps_and_uvs_wrap_around() {
+        let material = Material::new(
+            Vector3::new(1.0, 1.0, 1.0),
+            1.0,
+            0.0,
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.04, 0.04, 0.04),
+            0.0,
+            0.0,
+        );
+        let cylinder = Cylinder::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0), 1.0, 2.0, material);
+        let ray = |origin: Point3<f64>, direction: Vector3<f64>| Ray {
+            origin,
+            direction,
+            time: 0.0,
+        };
+        let hit = |ray: &Ray| cylinder.surface(ray, cylinder.intersection_distance(ray));
+
+        let left = ray(Point3::new(-5.0, 0.5, 0.0), Vector3::new(1.0, 0.0, 0.0));
+        assert!((cylinder.intersection_distance(&left) - 4.0).abs() < 1e-9);
+        let left = hit(&left);
+        assert!((left.normal - Vector3::new(-1.0, 0.0, 0.0)).norm() < 1e-9);
+        assert!(left.front_face);
+        assert!((left.uv.y - 0.25).abs() < 1e-9);
+
+        // Half a turn round the side is half way along the u coordinate.
+        let right = hit(&ray(Point3::new(5.0, 0.5, 0.0), Vector3::new(-1.0, 0.0, 0.0)));
+        assert!((right.normal - Vector3::new(1.0, 0.0, 0.0)).norm() < 1e-9);
+        assert!(((right.uv.x - left.uv.x).abs() - 0.5).abs() < 1e-9);
+
+        let top = hit(&ray(Point3::new(0.5, 5.0, 0.0), Vector3::new(0.0, -1.0, 0.0)));
+        assert!((top.normal - Vector3::new(0.0, 1.0, 0.0)).norm() < 1e-9);
+        assert!((top.uv.y - 0.5).abs() < 1e-9);
+
+        let bottom = hit(&ray(Point3::new(0.0, 1.0, 0.75), Vector3::new(0.0, -1.0, 0.0)));
+        assert!((bottom.normal - Vector3::new(0.0, -1.0, 0.0)).norm() < 1e-9);
+        assert!(!bottom.front_face);
+        assert!((bottom.uv.y - 0.75).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,129 @@
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::shape::{facing, Frame, Shape, Surface, SurfaceSample, BIAS};
+use nalgebra::{Point2, Point3, Vector3};
+use std::f64;
+
+pub struct Disk {
+    center: Point3<f64>,
+    frame: Frame,
+    normal: Vector3<f64>,
+    radius: f64,
+    material: Material,
+}
+
+impl Disk {
+    pub fn new(center: Point3<f64>, normal: Vector3<f64>, radius: f64, material: Material) -> Self {
+        Disk {
+            center,
+            frame: Frame::new(center, normal),
+            normal: normal.normalize(),
+            radius,
+            material,
+        }
+    }
+}
+
+impl Shape for Disk {
+    fn intersection_distance(&self, ray: &Ray) -> f64 {
+        let local = self.frame.ray_to_local(ray);
+        if local.direction.y.abs() < f64::EPSILON {
+            return f64::INFINITY;
+        }
+
+        let t = -local.origin.y / local.direction.y;
+        if t <= BIAS {
+            return f64::INFINITY;
+        }
+
+        let x = local.origin.x + local.direction.x * t;
+        let z = local.origin.z + local.direction.z * t;
+        if x * x + z * z > self.radius * self.radius {
+            f64::INFINITY
+        } else {
+            t
+        }
+    }
+
+    fn surface(&self, ray: &Ray, distance: f64) -> Surface {
+        let point = self.frame.point_to_local(&(ray.origin + ray.direction * distance));
+        let r = (point.x * point.x + point.z * point.z).sqrt();
+        let phi = point.z.atan2(point.x) + f64::consts::PI;
+        let (normal, front_face) = facing(self.normal, ray);
+        Surface {
+            normal,
+            uv: Point2::new(r / self.radius, phi / (2.0 * f64::consts::PI)),
+            front_face,
+        }
+    }
+
+    // Runs in circles around the centre, like the grooves of a record.
+    fn tangent(&self, point: &Point3<f64>, normal: &Vector3<f64>) -> Vector3<f64> {
+        let around = normal.cross(&(point - self.center));
+        if around.norm() > 1e-6 {
+            around.normalize()
+        } else {
+            self.frame.vector_to_world(&Vector3::new(1.0, 0.0, 0.0))
+        }
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn area(&self) -> f64 {
+        f64::consts::PI * self.radius * self.radius
+    }
+
+    fn sample_surface(&self) -> Option<SurfaceSample> {
+        let r = self.radius * rand::random::<f64>().sqrt();
+        let phi = 2.0 * f64::consts::PI * rand::random::<f64>();
+        let offset = self.frame.vector_to_world(&Vector3::new(r * phi.cos(), 0.0, r * phi.sin()));
+        Some(SurfaceSample {
+            point: self.center + offset,
+            normal: self.normal,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn normals_face_the_ray_and_uvs_run_out_from_the_centre() {
+        let material = Material::new(
+            Vector3::new(1.0, 1.0, 1.0),
+            1.0,
+            0.0,
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.04, 0.04, 0.04),
+            0.0,
+            0.0,
+        );
+        let disk = Disk::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0), 2.0, material);
+        let ray = |origin: Point3<f64>, direction: Vector3<f64>| Ray {
+            origin,
+            direction,
+            time: 0.0,
+        };
+
+        let above = ray(Point3::new(1.0, 1.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        assert!((disk.intersection_distance(&above) - 1.0).abs() < 1e-9);
+        let surface = disk.surface(&above, 1.0);
+        assert_eq!(surface.normal, Vector3::new(0.0, 1.0, 0.0));
+        assert!(surface.front_face);
+        assert!((surface.uv.x - 0.5).abs() < 1e-9);
+
+        // Across the centre the radius is the same and the angle half a turn on.
+        let opposite = ray(Point3::new(-1.0, -1.0, 0.0), Vector3::new(0.0, 1.0, 0.0));
+        let back = disk.surface(&opposite, disk.intersection_distance(&opposite));
+        assert_eq!(back.normal, Vector3::new(0.0, -1.0, 0.0));
+        assert!(!back.front_face);
+        assert!((back.uv.x - 0.5).abs() < 1e-9);
+        assert!(((back.uv.y - surface.uv.y).abs() - 0.5).abs() < 1e-9);
+
+        let outside = ray(Point3::new(2.5, 1.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        assert_eq!(disk.intersection_distance(&outside), f64::INFINITY);
+    }
+}
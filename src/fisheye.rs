@@ -0,0 +1,61 @@
+use crate::camera::{image_point, Camera, View};
+use crate::ray::Ray;
+use nalgebra::Vector3;
+use std::f64;
+
+// How the angle from the view direction maps to distance from the centre of
+// the image circle.
+#[derive(Copy, Clone)]
+pub enum Mapping {
+    // Distance proportional to the angle.
+    Equidistant,
+    // Equal areas of the image cover equal solid angles.
+    Equisolid,
+}
+
+// A circular fisheye image which fits the shorter side of the frame. Pixels
+// outside the circle see nothing.
+pub struct Fisheye {
+    view: View,
+    field_of_view: f64,
+    mapping: Mapping,
+}
+
+impl Fisheye {
+    // `field_of_view` is the angle across the image circle in degrees, and
+    // may reach 360.
+    pub fn new(view: View, field_of_view: f64, mapping: Mapping) -> Self {
+        Fisheye {
+            view,
+            field_of_view: field_of_view.to_radians(),
+            mapping,
+        }
+    }
+}
+
+impl Camera for Fisheye {
+    fn ray(&self, x: usize, y: usize, width: usize, height: usize) -> Option<Ray> {
+        let (u, v) = image_point(x, y, width, height);
+        let scale = width.min(height) as f64;
+        let px = (u - 0.5) * width as f64 / scale * 2.0;
+        let py = (0.5 - v) * height as f64 / scale * 2.0;
+        let radius = (px * px + py * py).sqrt();
+        if radius > 1.0 {
+            return None;
+        }
+
+        let edge = self.field_of_view * 0.5;
+        let theta = match self.mapping {
+            Mapping::Equidistant => radius * edge,
+            Mapping::Equisolid => 2.0 * (radius * (edge * 0.5).sin()).min(1.0).asin(),
+        };
+        let phi = py.atan2(px);
+        let local = Vector3::new(theta.sin() * phi.cos(), theta.sin() * phi.sin(), -theta.cos());
+
+        Some(Ray {
+            origin: self.view.position,
+            direction: self.view.to_world(&local),
+            time: 0.0,
+        })
+    }
+}
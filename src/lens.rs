@@ -0,0 +1,344 @@
+use crate::camera::{image_point, Camera, View};
+use crate::ray::Ray;
+use nalgebra::{Point3, Vector3};
+
+// Widest image, as a multiple of its height, whose corners are sure to be
+// reached by light through the lens.
+const MAX_ASPECT: f64 = 2.0;
+
+// One surface of a lens prescription. A radius of zero marks the aperture
+// stop, and the index is that of the glass between this surface and the next
+// one towards the sensor, with zero or one meaning air.
+#[derive(Copy, Clone)]
+pub struct LensElement {
+    pub radius: f64,
+    pub thickness: f64,
+    pub index: f64,
+    pub aperture: f64,
+}
+
+// Reads a lens table as used by PBRT and in lens design books: a row for
+// each surface from the front of the lens to the back, giving the radius of
+// curvature, the distance to the next surface, the refractive index behind
+// it and its aperture diameter, all in millimetres. Anything after a # is
+// ignored.
+pub fn parse_lens_table(text: &str) -> Option<Vec<LensElement>> {
+    let mut elements = vec![];
+    for line in text.lines() {
+        let values = line
+            .split('#')
+            .next()
+            .unwrap_or("")
+            .split_whitespace()
+            .map(|value| value.parse::<f64>().ok())
+            .collect::<Option<Vec<f64>>>()?;
+        match values.as_slice() {
+            [] => {}
+            [radius, thickness, index, aperture] => elements.push(LensElement {
+                radius: radius * 1e-3,
+                thickness: thickness * 1e-3,
+                index: *index,
+                aperture: aperture * 1e-3,
+            }),
+            _ => return None,
+        }
+    }
+
+    if elements.is_empty() {
+        None
+    } else {
+        Some(elements)
+    }
+}
+
+// A camera which traces rays from the sensor through each surface of a real
+// lens, so that distortion, vignetting and the change in framing as the lens
+// focuses all come out of the glass. Lens space has the sensor at the origin
+// and the lens in front of it down negative z.
+pub struct RealisticLens {
+    view: View,
+    elements: Vec<LensElement>,
+    sensor: f64,
+    // Radius of the part of the rear element which any light gets through.
+    pupil: f64,
+}
+
+impl RealisticLens {
+    // Moves the lens away from the sensor to bring the plane `focus` in
+    // front of the sensor into focus, failing if the lens can't focus that
+    // close or no light gets through it.
+    pub fn new(view: View, elements: Vec<LensElement>, sensor: f64, focus: f64) -> Result<Self, String> {
+        if elements.is_empty() {
+            return Err("the lens has no elements".to_string());
+        }
+        let mut camera = RealisticLens {
+            view,
+            elements,
+            sensor,
+            pupil: 0.0,
+        };
+        camera.focus(focus)?;
+        Ok(camera)
+    }
+
+    // Moves the lens to focus at the distance, leaving it where it was if it
+    // can't.
+    fn focus(&mut self, focus: f64) -> Result<f64, String> {
+        let last = self.elements.len() - 1;
+        let previous = self.elements[last].thickness;
+        self.elements[last].thickness = 0.0;
+
+        let pupil = match self.film_distance(focus) {
+            Some(distance) => {
+                self.elements[last].thickness = distance;
+                self.exit_pupil().ok_or_else(|| "no light gets through the lens".to_string())
+            }
+            None => Err(format!("the lens can't focus at {}", focus)),
+        };
+        match pupil {
+            Ok(pupil) => {
+                self.pupil = pupil;
+                Ok(focus)
+            }
+            Err(error) => {
+                self.elements[last].thickness = previous;
+                Err(error)
+            }
+        }
+    }
+
+    // Treats the lens as a thick lens, finding its principal planes and
+    // focal length by tracing rays parallel to the axis through it from each
+    // side, and solves for the distance behind the rear element at which the
+    // plane comes into focus.
+    fn film_distance(&self, focus: f64) -> Option<f64> {
+        let height = self.sensor * 1e-3;
+        let parallel = |z: f64, direction: f64| Ray {
+            origin: Point3::new(height, 0.0, z),
+            direction: Vector3::new(0.0, 0.0, direction),
+            time: 0.0,
+        };
+        let (rear_principal, rear_focal) =
+            cardinal_points(height, &self.trace_from_scene(parallel(self.front_z() - 1.0, 1.0))?);
+        let (front_principal, _) = cardinal_points(height, &self.trace_from_sensor(parallel(1.0, -1.0))?);
+
+        let focal_length = rear_focal - rear_principal;
+        let span = front_principal - rear_principal + focus;
+        let discriminant = span * span - 4.0 * span * focal_length;
+        if focal_length <= 0.0 || discriminant < 0.0 {
+            return None;
+        }
+
+        let image_distance = 0.5 * (span - discriminant.sqrt());
+        let distance = image_distance + rear_principal;
+        if distance > 0.0 {
+            Some(distance)
+        } else {
+            None
+        }
+    }
+
+    // Finds how far from the axis light leaves the rear element, for points
+    // anywhere on a sensor up to twice as wide as it is high. The lens is
+    // round, so points out to the corners along one axis cover them all.
+    fn exit_pupil(&self) -> Option<f64> {
+        let rear = self.elements[self.elements.len() - 1].aperture / 2.0;
+        let corner = 0.5 * self.sensor * (MAX_ASPECT * MAX_ASPECT + 1.0).sqrt();
+        let steps = 64;
+        let mut pupil: Option<f64> = None;
+
+        for film in 0..16 {
+            let film_point = Point3::new(film as f64 / 15.0 * corner, 0.0, 0.0);
+            for i in 0..steps {
+                for j in 0..steps {
+                    let x = (i as f64 + 0.5) / steps as f64 * 2.0 - 1.0;
+                    let y = (j as f64 + 0.5) / steps as f64 * 2.0 - 1.0;
+                    let point = Point3::new(x * rear, y * rear, self.rear_z());
+                    let ray = Ray {
+                        origin: film_point,
+                        direction: (point - film_point).normalize(),
+                        time: 0.0,
+                    };
+                    if (x * x + y * y) <= 1.0 && self.trace_from_sensor(ray).is_some() {
+                        let radius = (point.x * point.x + point.y * point.y).sqrt();
+                        pupil = Some(pupil.map_or(radius, |pupil| pupil.max(radius)));
+                    }
+                }
+            }
+        }
+
+        pupil.map(|pupil| (pupil + 2.0 * rear / steps as f64).min(rear))
+    }
+
+    fn rear_z(&self) -> f64 {
+        -self.elements[self.elements.len() - 1].thickness
+    }
+
+    fn front_z(&self) -> f64 {
+        -self.elements.iter().map(|element| element.thickness).sum::<f64>()
+    }
+
+    // Follows a ray from the sensor out through the front of the lens, or
+    // gives nothing if it hits the barrel or the stop on the way.
+    fn trace_from_sensor(&self, mut ray: Ray) -> Option<Ray> {
+        let mut z = 0.0;
+        for (i, element) in self.elements.iter().enumerate().rev() {
+            z -= element.thickness;
+            let outside = if i > 0 { self.elements[i - 1].index } else { 1.0 };
+            ray = pass(element, z, ray, air(element.index), air(outside))?;
+        }
+        Some(ray)
+    }
+
+    // Follows a ray from the scene in through the front of the lens.
+    fn trace_from_scene(&self, mut ray: Ray) -> Option<Ray> {
+        let mut z = self.front_z();
+        for (i, element) in self.elements.iter().enumerate() {
+            let outside = if i > 0 { self.elements[i - 1].index } else { 1.0 };
+            ray = pass(element, z, ray, air(outside), air(element.index))?;
+            z += element.thickness;
+        }
+        Some(ray)
+    }
+}
+
+impl Camera for RealisticLens {
+    fn focus_on(&mut self, point: &Point3<f64>) -> Option<f64> {
+        let depth = -self.view.to_local(&(point - self.view.position)).z;
+        if depth > 0.0 {
+            self.focus(depth).ok()
+        } else {
+            None
+        }
+    }
+
+    fn ray(&self, x: usize, y: usize, width: usize, height: usize) -> Option<Ray> {
+        let aspect = width as f64 / height as f64;
+        let (u, v) = image_point(x, y, width, height);
+        let film_point = Point3::new(-(u - 0.5) * aspect * self.sensor, (v - 0.5) * self.sensor, 0.0);
+
+        let radius = self.pupil * rand::random::<f64>().sqrt();
+        let angle = 2.0 * std::f64::consts::PI * rand::random::<f64>();
+        let pupil_point = Point3::new(radius * angle.cos(), radius * angle.sin(), self.rear_z());
+        let direction = (pupil_point - film_point).normalize();
+
+        // Light reaching the sensor at an angle is spread over more of it,
+        // which is kept by dropping rays rather than weighting them.
+        if rand::random::<f64>() > direction.z.powi(4) {
+            return None;
+        }
+
+        let ray = self.trace_from_sensor(Ray {
+            origin: film_point,
+            direction,
+            time: 0.0,
+        })?;
+        Some(Ray {
+            origin: self.view.position + self.view.to_world(&ray.origin.coords),
+            direction: self.view.to_world(&ray.direction),
+            time: 0.0,
+        })
+    }
+}
+
+fn air(index: f64) -> f64 {
+    if index == 0.0 {
+        1.0
+    } else {
+        index
+    }
+}
+
+// Where a ray which entered the lens parallel to the axis at `height` crosses
+// the axis, and where it would have bent to do so in a single thin lens.
+fn cardinal_points(height: f64, ray: &Ray) -> (f64, f64) {
+    let along = |t: f64| ray.origin.z + t * ray.direction.z;
+    let focal = along(-ray.origin.x / ray.direction.x);
+    let principal = along((height - ray.origin.x) / ray.direction.x);
+    (principal, focal)
+}
+
+// Takes a ray through one surface at `z`, refracting it from one index to
+// the other.
+fn pass(element: &LensElement, z: f64, ray: Ray, from: f64, to: f64) -> Option<Ray> {
+    let (distance, normal) = if element.radius == 0.0 {
+        ((z - ray.origin.z) / ray.direction.z, None)
+    } else {
+        let centre = Point3::new(0.0, 0.0, z + element.radius);
+        let (distance, normal) = hit_sphere(&centre, element.radius, &ray)?;
+        (distance, Some(normal))
+    };
+    if distance.is_nan() || distance <= 0.0 {
+        return None;
+    }
+
+    let point = ray.origin + ray.direction * distance;
+    let limit = element.aperture / 2.0;
+    if point.x * point.x + point.y * point.y > limit * limit {
+        return None;
+    }
+
+    let direction = match normal {
+        None => ray.direction,
+        Some(normal) => refract(&ray.direction, &normal, from / to)?,
+    };
+    Some(Ray {
+        origin: point,
+        direction,
+        time: ray.time,
+    })
+}
+
+// The distance along the ray to the side of the sphere which belongs to the
+// surface, and the normal there facing back along the ray.
+fn hit_sphere(centre: &Point3<f64>, radius: f64, ray: &Ray) -> Option<(f64, Vector3<f64>)> {
+    let offset = ray.origin - centre;
+    let b = offset.dot(&ray.direction);
+    let c = offset.norm_squared() - radius * radius;
+    let discriminant = b * b - c;
+    if discriminant < 0.0 {
+        return None;
+    }
+
+    let root = discriminant.sqrt();
+    let closer = (ray.direction.z > 0.0) != (radius < 0.0);
+    let distance = if closer { -b - root } else { -b + root };
+    let normal = (offset + ray.direction * distance).normalize();
+    let normal = if normal.dot(&ray.direction) > 0.0 { -normal } else { normal };
+    Some((distance, normal))
+}
+
+fn refract(direction: &Vector3<f64>, normal: &Vector3<f64>, ratio: f64) -> Option<Vector3<f64>> {
+    let cos_incident = -normal.dot(direction);
+    let k = 1.0 - ratio * ratio * (1.0 - cos_incident * cos_incident);
+    if k < 0.0 {
+        return None;
+    }
+    Some((direction * ratio + normal * (ratio * cos_incident - k.sqrt())).normalize())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rays_from_a_point_on_the_sensor_meet_at_the_focus_distance() {
+        let elements = parse_lens_table(include_str!("../lenses/dgauss.50mm.dat")).unwrap();
+        let view = View::look_at(Point3::origin(), Point3::new(0.0, 0.0, -1.0), Vector3::new(0.0, 1.0, 0.0));
+        let camera = RealisticLens::new(view, elements, 0.024, 2.0).unwrap();
+
+        let spread = |depth: f64| {
+            let hits: Vec<Point3<f64>> = (0..2000)
+                .filter_map(|_| camera.ray(500, 500, 1001, 1001))
+                .map(|ray| ray.origin + ray.direction * ((-depth - ray.origin.z) / ray.direction.z))
+                .collect();
+            hits.iter().map(|hit| (hit.x * hit.x + hit.y * hit.y).sqrt()).fold(0.0, f64::max)
+        };
+        let focused = spread(2.0);
+        assert!(focused < 0.005);
+        assert!(focused * 2.0 < spread(1.0) && focused * 2.0 < spread(4.0));
+
+        // Light still reaches the corners of a wide image.
+        assert!((0..2000).filter_map(|_| camera.ray(0, 0, 200, 100)).count() > 0);
+    }
+}
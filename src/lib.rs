@@ -2,13 +2,42 @@ use crate::canvas_renderer::CanvasRenderer;
 use crate::tracer::Tracer;
 use wasm_bindgen::prelude::*;
 
+mod animation;
+mod aperture;
 mod camera;
 pub mod canvas_renderer;
+mod conductor;
+mod cone;
+mod csg;
+mod cuboid;
+mod cylinder;
+mod disk;
+mod fisheye;
+mod lens;
 mod material;
+mod measured;
+mod medium;
+mod motion;
+mod nodes;
+mod orthographic;
+mod panorama;
+mod plane;
 mod ray;
+mod rectangle;
 mod scene;
+mod scene_file;
+mod sdf;
+pub mod sequence;
+mod shape;
 mod sphere;
+mod spectrum;
+mod stereo;
 pub mod scene_loader;
+mod texture;
+mod thin_film;
+mod torus;
+mod triangle;
+mod volume;
 pub mod tracer;
 
 #[wasm_bindgen(start)]
@@ -1,21 +1,92 @@
-use nalgebra::{geometry::Reflection, Unit, Vector3};
-use rand;
+use crate::conductor::Conductor;
+use crate::measured::MeasuredBrdf;
+use crate::medium::Medium;
+use crate::nodes::{NodeGraph, ShadingPoint};
+use crate::spectrum::Dispersion;
+use crate::texture::Texture;
+use crate::thin_film::ThinFilm;
+use nalgebra::{geometry::Reflection, Point2, Unit, Vector3};
+use std::borrow::Cow;
 use std::f64;
+use std::rc::Rc;
 
-pub struct BSDF {
+pub struct Bsdf {
     pub direction: Vector3<f64>,
     pub signal: Vector3<f64>,
+    pub diffuse: bool,
 }
 
 #[derive(Copy, Clone)]
+pub enum Emission {
+    Radiance(Vector3<f64>),
+    Power(Vector3<f64>),
+}
+
+// A clear varnish over the rest of the material.
+#[derive(Copy, Clone)]
+struct Clearcoat {
+    reflectance: f64,
+    gloss: f64,
+}
+
+// Microfacets stretched along the surface tangent, as on brushed metal, with
+// separate roughness along (`alpha_x`) and across (`alpha_y`) it.
+#[derive(Copy, Clone)]
+struct Anisotropy {
+    alpha_x: f64,
+    alpha_y: f64,
+}
+
+// How much of the surface is really there, so that flat cards can stand in
+// for leaves or fences.
+#[derive(Clone)]
+enum Opacity {
+    // Hits are kept with a probability equal to the opacity.
+    Stochastic(Texture),
+    // Hits are kept wherever the opacity reaches the threshold.
+    Cutout(Texture, f64),
+}
+
+// Material parameters which can be driven by a shader node.
+#[derive(Copy, Clone, PartialEq)]
+pub enum Input {
+    Color,
+    Transparency,
+    Metal,
+    Gloss,
+    Roughness,
+}
+
+// Nodes feeding material inputs, evaluated afresh at every intersection.
+// Each input keeps the graph its node belongs to.
+#[derive(Clone)]
+struct Shader {
+    bindings: Vec<(Input, Rc<NodeGraph>, usize)>,
+}
+
+#[derive(Clone)]
 pub struct Material {
     color: Vector3<f64>,
     refraction: f64,
     transparency: f64,
-    light: Vector3<f64>,
+    emission: Emission,
+    two_sided: bool,
     frensel: Vector3<f64>,
     metal: f64,
     gloss: f64,
+    interior: Option<Medium>,
+    subsurface: Option<Medium>,
+    dispersion: Option<Dispersion>,
+    film: Option<ThinFilm>,
+    conductor: Option<Conductor>,
+    clearcoat: Option<Clearcoat>,
+    sheen: Option<Vector3<f64>>,
+    roughness: f64,
+    anisotropy: Option<Anisotropy>,
+    tangent_rotation: Option<Texture>,
+    opacity: Option<Opacity>,
+    measured: Option<Rc<MeasuredBrdf>>,
+    shader: Option<Shader>,
 }
 
 impl Material {
@@ -32,87 +103,535 @@ impl Material {
             color,
             refraction,
             transparency,
-            light,
+            emission: Emission::Radiance(light),
+            two_sided: false,
             frensel,
             metal,
             gloss,
+            interior: None,
+            subsurface: None,
+            dispersion: None,
+            film: None,
+            conductor: None,
+            clearcoat: None,
+            sheen: None,
+            roughness: 0.0,
+            anisotropy: None,
+            tangent_rotation: None,
+            opacity: None,
+            measured: None,
+            shader: None,
         }
     }
 
-    pub fn emit(&self, normal: &Vector3<f64>, direction: &Vector3<f64>) -> Option<Vector3<f64>> {
-        if self.light.max() == 0f64 {
-            None
-        } else {
-            Some(self.light * f64::max(normal.dot(&-direction), 0f64))
+    // Fills the inside of any closed shape using this material.
+    pub fn with_interior(mut self, medium: Medium) -> Self {
+        self.interior = Some(medium);
+        self
+    }
+
+    pub fn interior(&self) -> Option<Medium> {
+        self.interior.clone()
+    }
+
+    // Light which isn't reflected off the surface scatters around beneath it,
+    // travelling on average `mean_free_path` between scattering events and
+    // keeping `albedo` of its energy each time.
+    pub fn with_subsurface(mut self, albedo: Vector3<f64>, mean_free_path: Vector3<f64>) -> Self {
+        let extinction = mean_free_path.map(|length| 1.0 / length);
+        let scattering = albedo.component_mul(&extinction);
+        self.subsurface = Some(Medium::new(extinction - scattering, scattering, 0.0));
+        self
+    }
+
+    pub fn subsurface(&self) -> Option<&Medium> {
+        self.subsurface.as_ref()
+    }
+
+    // Only takes effect when rendering spectrally. Otherwise the single
+    // refractive index given to `new` is used.
+    pub fn with_dispersion(mut self, dispersion: Dispersion) -> Self {
+        self.dispersion = Some(dispersion);
+        self
+    }
+
+    pub fn is_dispersive(&self) -> bool {
+        self.dispersion.is_some()
+    }
+
+    // Coats the surface with a film whose reflections replace the plain
+    // Fresnel term.
+    pub fn with_thin_film(mut self, film: ThinFilm) -> Self {
+        self.film = Some(film);
+        self
+    }
+
+    // Makes the surface a metal, reflecting with the exact Fresnel term for
+    // its complex refractive index rather than with Schlick's approximation.
+    pub fn with_conductor(mut self, conductor: Conductor) -> Self {
+        self.conductor = Some(conductor);
+        self.metal = 1.0;
+        self
+    }
+
+    // Varnishes the material with a clear layer of the given refractive index,
+    // which has its own glossiness. Light not reflected by the coat reaches
+    // the material beneath.
+    pub fn with_clearcoat(mut self, index: f64, gloss: f64) -> Self {
+        let root = (index - 1.0) / (index + 1.0);
+        self.clearcoat = Some(Clearcoat {
+            reflectance: root * root,
+            gloss,
+        });
+        self
+    }
+
+    // Fibres standing up from fabrics such as velvet scatter light back at
+    // grazing angles, giving a soft coloured rim.
+    pub fn with_sheen(mut self, color: Vector3<f64>) -> Self {
+        self.sheen = Some(color);
+        self
+    }
+
+    // Gives the diffuse lobe Oren-Nayar's microfacet roughness, where
+    // `roughness` is the standard deviation of the facet slopes in radians.
+    // Rough surfaces such as clay look flatter, scattering more light back
+    // towards a viewer near the light.
+    pub fn with_diffuse_roughness(mut self, roughness: f64) -> Self {
+        self.roughness = roughness;
+        self
+    }
+
+    // Replaces the glossy cone of reflections with an anisotropic GGX
+    // distribution, lined up with the tangent of the surface.
+    pub fn with_anisotropy(mut self, alpha_x: f64, alpha_y: f64) -> Self {
+        self.anisotropy = Some(Anisotropy { alpha_x, alpha_y });
+        self
+    }
+
+    // Turns the tangent about the normal by an angle in radians looked up
+    // from the texture.
+    pub fn with_tangent_rotation(mut self, rotation: Texture) -> Self {
+        self.tangent_rotation = Some(rotation);
+        self
+    }
+
+    // Reflects using a measured BRDF in place of every other surface term.
+    pub fn with_measured(mut self, brdf: Rc<MeasuredBrdf>) -> Self {
+        self.measured = Some(brdf);
+        self
+    }
+
+    // Drives an input from the output of a node in the graph, overriding the
+    // value it was given.
+    pub fn with_input(mut self, input: Input, graph: Rc<NodeGraph>, node: usize) -> Self {
+        let mut shader = self.shader.take().unwrap_or(Shader { bindings: vec![] });
+        shader.bindings.push((input, graph, node));
+        self.shader = Some(shader);
+        self
+    }
+
+    // The material with any shader inputs evaluated at a point.
+    pub fn shade(&self, point: &ShadingPoint) -> Cow<'_, Material> {
+        let shader = match &self.shader {
+            Some(shader) => shader,
+            None => return Cow::Borrowed(self),
+        };
+
+        let mut shaded = self.clone();
+        shaded.shader = None;
+        for (input, graph, node) in &shader.bindings {
+            let value = graph.evaluate(*node, point);
+            let scalar = value.average().clamp(0.0, 1.0);
+            match input {
+                Input::Color => shaded.color = value,
+                Input::Transparency => shaded.transparency = scalar,
+                Input::Metal => shaded.metal = scalar,
+                Input::Gloss => shaded.gloss = scalar,
+                Input::Roughness => shaded.roughness = value.average().max(0.0),
+            }
         }
+        Cow::Owned(shaded)
+    }
+
+    // Lets rays pass through the surface where it is partly transparent,
+    // averaging out over many samples.
+    pub fn with_opacity(mut self, opacity: Texture) -> Self {
+        self.opacity = Some(Opacity::Stochastic(opacity));
+        self
+    }
+
+    // Cuts away the surface wherever the opacity falls below `threshold`.
+    pub fn with_cutout(mut self, opacity: Texture, threshold: f64) -> Self {
+        self.opacity = Some(Opacity::Cutout(opacity, threshold));
+        self
+    }
+
+    pub fn has_opacity(&self) -> bool {
+        self.opacity.is_some()
+    }
+
+    // Whether a ray hitting the surface at `uv` stops there.
+    pub fn is_opaque_at(&self, uv: &Point2<f64>) -> bool {
+        match &self.opacity {
+            None => true,
+            Some(Opacity::Stochastic(opacity)) => rand::random::<f64>() < opacity.value(uv),
+            Some(Opacity::Cutout(opacity, threshold)) => opacity.value(uv) >= *threshold,
+        }
+    }
+
+    fn refractive_index(&self, wavelength: Option<f64>) -> f64 {
+        match (self.dispersion, wavelength) {
+            (Some(dispersion), Some(wavelength)) => dispersion.index(wavelength),
+            _ => self.refraction,
+        }
+    }
+
+    // Surfaces which neither reflect nor bend light, such as the boundary of
+    // a volume, let rays straight through.
+    pub fn is_null(&self) -> bool {
+        self.transparency >= 1.0
+            && self.refraction == 1.0
+            && self.frensel.max() == 0.0
+            && self.film.is_none()
+            && !self.is_emissive()
+    }
+
+    pub fn emitting(mut self, emission: Emission, two_sided: bool) -> Self {
+        self.emission = emission;
+        self.two_sided = two_sided;
+        self
+    }
+
+    pub fn is_emissive(&self) -> bool {
+        match self.emission {
+            Emission::Radiance(light) | Emission::Power(light) => light.max() > 0f64,
+        }
+    }
+
+    // Power is spread evenly over every emitting side of the shape.
+    pub fn emit(&self, front_face: bool, area: f64) -> Option<Vector3<f64>> {
+        if !front_face && !self.two_sided {
+            return None;
+        }
+
+        match self.emission {
+            Emission::Radiance(light) if light.max() > 0f64 => Some(light),
+            Emission::Power(power) if power.max() > 0f64 && area > 0f64 => {
+                let sides = if self.two_sided { 2.0 } else { 1.0 };
+                Some(power / (f64::consts::PI * area * sides))
+            }
+            _ => None,
+        }
+    }
+
+    // Matches the weight given to cosine sampled directions by `diffused`,
+    // for light arriving from `direction` and leaving towards `view`.
+    pub fn diffuse_response(
+        &self,
+        normal: &Vector3<f64>,
+        view: &Vector3<f64>,
+        direction: &Vector3<f64>,
+    ) -> Vector3<f64> {
+        let cos_theta = f64::max(normal.dot(direction), 0f64);
+        if let Some(measured) = &self.measured {
+            return measured.evaluate(normal, view, direction) * cos_theta;
+        }
+        let roughness = self.oren_nayar(normal, view, direction);
+        self.color * (roughness * cos_theta / (f64::consts::PI * f64::consts::PI))
+    }
+
+    // Solid angle density with which diffuse bounces seen from `view` pick
+    // `direction`.
+    pub fn scatter_pdf(&self, normal: &Vector3<f64>, view: &Vector3<f64>, direction: &Vector3<f64>) -> f64 {
+        match &self.measured {
+            Some(measured) => measured.pdf(normal, view, direction),
+            None => f64::max(normal.dot(direction), 0.0) / f64::consts::PI,
+        }
+    }
+
+    // Scale of the Oren-Nayar lobe relative to a Lambertian one.
+    fn oren_nayar(&self, normal: &Vector3<f64>, view: &Vector3<f64>, direction: &Vector3<f64>) -> f64 {
+        if self.roughness == 0.0 {
+            return 1.0;
+        }
+
+        let sigma2 = self.roughness * self.roughness;
+        let a = 1.0 - 0.5 * sigma2 / (sigma2 + 0.33);
+        let b = 0.45 * sigma2 / (sigma2 + 0.09);
+
+        let cos_view = normal.dot(view).clamp(0.0, 1.0);
+        let cos_light = normal.dot(direction).clamp(0.0, 1.0);
+        let sin_view = (1.0 - cos_view * cos_view).sqrt();
+        let sin_light = (1.0 - cos_light * cos_light).sqrt();
+
+        // Cosine of the azimuth between the two directions.
+        let view_tangent = view - normal * cos_view;
+        let light_tangent = direction - normal * cos_light;
+        let cos_azimuth = if sin_view > 1e-6 && sin_light > 1e-6 {
+            (view_tangent.dot(&light_tangent) / (sin_view * sin_light)).max(0.0)
+        } else {
+            0.0
+        };
+
+        // Sine of the larger angle from the normal and tangent of the smaller.
+        let (sin_alpha, tan_beta) = if cos_view < cos_light {
+            (sin_view, sin_light / cos_light.max(1e-6))
+        } else {
+            (sin_light, sin_view / cos_view.max(1e-6))
+        };
+
+        a + b * cos_azimuth * sin_alpha * tan_beta
     }
 
     pub fn bsdf(
         &self,
         normal: &Vector3<f64>,
+        tangent: &Vector3<f64>,
         direction: &Vector3<f64>,
+        uv: &Point2<f64>,
         length: f64,
-    ) -> Option<BSDF> {
-        let entering = direction.dot(&normal) < 0f64;
+        wavelength: Option<f64>,
+    ) -> Option<Bsdf> {
+        if self.is_null() {
+            return Some(Bsdf {
+                direction: *direction,
+                signal: Vector3::new(1.0, 1.0, 1.0),
+                diffuse: false,
+            });
+        }
+
+        let entering = direction.dot(normal) < 0f64;
         if entering {
+            if let Some(measured) = &self.measured {
+                return measured.sample(normal, &-direction).map(|(direction, signal)| Bsdf {
+                    direction,
+                    signal,
+                    diffuse: true,
+                });
+            }
+
+            if let Some(layer) = self.layer(normal, direction) {
+                return Some(layer);
+            }
+
             let mut test = FilteredProbabilityTest::new();
-            if test.or(self.schilck(&normal, &direction).average()) {
-                Some(self.reflected(*direction, &normal))
+            let fresnel = self.fresnel(normal, direction, uv);
+            if test.or(fresnel.average()) {
+                self.reflected(*direction, normal, tangent, uv, &fresnel)
+            } else if self.subsurface.is_some() {
+                Some(self.entered(normal))
             } else if test.or(self.transparency) {
-                Some(self.refracted_entry(*direction, &normal))
+                // Wavelengths for which the glass is less dense than air
+                // can't get in, and are reflected instead.
+                self.refracted_entry(*direction, normal, wavelength)
+                    .or_else(|| self.reflected(*direction, normal, tangent, uv, &fresnel))
             } else if test.or(self.metal) {
                 None
             } else {
-                Some(self.diffused(&normal))
+                Some(self.diffused(normal, &-direction))
             }
-        } else if let Some(exited) = direction.refraction(&-normal, self.refraction, 1.0) {
-            Some(self.refracted_exit(exited, length))
         } else {
-            None
+            direction
+                .refraction(&-normal, self.refractive_index(wavelength), 1.0)
+                .map(|exited| self.refracted_exit(exited, length))
+        }
+    }
+
+    // Gives the outer layers a chance to scatter the light before it reaches
+    // the base material, each in proportion to how much it reflects.
+    fn layer(&self, normal: &Vector3<f64>, direction: &Vector3<f64>) -> Option<Bsdf> {
+        let cos_incident = (-direction).dot(normal);
+        let grazing = (1.0 - cos_incident).powf(5.0);
+
+        if let Some(coat) = self.clearcoat {
+            let fresnel = coat.reflectance + (1.0 - coat.reflectance) * grazing;
+            if rand::random::<f64>() < fresnel {
+                let mut reflected = *direction;
+                Reflection::new(Unit::new_normalize(*normal), 0.0).reflect(&mut reflected);
+                return Some(Bsdf {
+                    direction: random_in_cone(&reflected, 1.0 - coat.gloss),
+                    signal: Vector3::new(1.0, 1.0, 1.0),
+                    diffuse: false,
+                });
+            }
+        }
+
+        if let Some(sheen) = self.sheen {
+            let reflectance = sheen * grazing;
+            if rand::random::<f64>() < reflectance.average() {
+                return Some(Bsdf {
+                    direction: random_in_cos_hemisphere(normal),
+                    signal: reflectance / (reflectance.average() * f64::consts::PI),
+                    diffuse: false,
+                });
+            }
+        }
+
+        None
+    }
+
+    fn fresnel(&self, normal: &Vector3<f64>, direction: &Vector3<f64>, uv: &Point2<f64>) -> Vector3<f64> {
+        let cos_incident = (-direction).dot(normal);
+        match (&self.film, &self.conductor) {
+            (Some(film), _) => film.reflectance(cos_incident, &self.normal_reflectance(), uv),
+            (None, Some(conductor)) => conductor.reflectance(cos_incident),
+            (None, None) => self.schilck(normal, direction),
+        }
+    }
+
+    fn normal_reflectance(&self) -> Vector3<f64> {
+        match &self.conductor {
+            Some(conductor) => conductor.reflectance(1.0),
+            None => self.frensel,
         }
     }
 
     fn schilck(&self, incident: &Vector3<f64>, normal: &Vector3<f64>) -> Vector3<f64> {
-        let cos_incident = (-incident).dot(&normal);
+        let cos_incident = (-incident).dot(normal);
         self.frensel + ((Vector3::new(1.0, 1.0, 1.0) - self.frensel) * (1.0 - cos_incident).powf(5.0))
     }
 
-    fn diffused(&self, normal: &Vector3<f64>) -> BSDF {
+    // Bounces light seen from `view` in a random cosine weighted direction.
+    pub fn diffused(&self, normal: &Vector3<f64>, view: &Vector3<f64>) -> Bsdf {
         let pdf = std::f64::consts::PI;
-        BSDF {
-            direction: random_in_cos_hemisphere(normal),
-            signal: self.color * (1.0 / pdf),
+        let direction = random_in_cos_hemisphere(normal);
+        let roughness = self.oren_nayar(normal, view, &direction);
+        Bsdf {
+            direction,
+            signal: self.color * (roughness / pdf),
+            diffuse: true,
+        }
+    }
+
+    // Enters a subsurface material diffusely.
+    fn entered(&self, normal: &Vector3<f64>) -> Bsdf {
+        Bsdf {
+            direction: random_in_cos_hemisphere(&-normal),
+            signal: Vector3::new(1.0, 1.0, 1.0),
+            diffuse: false,
         }
     }
 
-    fn reflected(&self, mut direction: Vector3<f64>, normal: &Vector3<f64>) -> BSDF {
+    fn reflected(
+        &self,
+        mut direction: Vector3<f64>,
+        normal: &Vector3<f64>,
+        tangent: &Vector3<f64>,
+        uv: &Point2<f64>,
+        fresnel: &Vector3<f64>,
+    ) -> Option<Bsdf> {
+        // Interference and conductors colour the reflection, which was
+        // chosen with the average of the channels.
+        let signal = if self.film.is_some() || self.conductor.is_some() {
+            fresnel / fresnel.average()
+        } else {
+            Vector3::new(1.0, 1.0, 1.0).lerp(&self.frensel, self.metal)
+        };
+
+        if let Some(anisotropy) = self.anisotropy {
+            let tangent = self.rotated_tangent(normal, tangent, uv);
+            return anisotropy
+                .reflect(&direction, normal, &tangent)
+                .map(|(direction, weight)| Bsdf {
+                    direction,
+                    signal: signal * weight,
+                    diffuse: false,
+                });
+        }
+
         Reflection::new(Unit::new_normalize(*normal), 0.0)
             .reflect(&mut direction);
 
-        BSDF{
+        Some(Bsdf{
             direction: random_in_cone(&direction, 1.0 - self.gloss),
-            signal: Vector3::new(1.0, 1.0, 1.0).lerp(&self.frensel, self.metal)
-        }
+            signal,
+            diffuse: false,
+        })
     }
 
-    fn refracted_entry(&self, direction: Vector3<f64>, normal: &Vector3<f64>) -> BSDF {
-        BSDF{
-            direction: direction.refraction(normal, 1.0, self.refraction).unwrap(),
-            signal: Vector3::new(1.0, 1.0, 1.0)
+    // The tangent made perpendicular to the normal and turned by any
+    // rotation texture.
+    fn rotated_tangent(&self, normal: &Vector3<f64>, tangent: &Vector3<f64>, uv: &Point2<f64>) -> Vector3<f64> {
+        let tangent = (tangent - normal * normal.dot(tangent)).normalize();
+        match &self.tangent_rotation {
+            Some(rotation) => {
+                let angle = rotation.value(uv);
+                tangent * angle.cos() + normal.cross(&tangent) * angle.sin()
+            }
+            None => tangent,
         }
     }
 
-    fn refracted_exit(&self, exited: Vector3<f64>, length: f64) -> BSDF {
+    fn refracted_entry(&self, direction: Vector3<f64>, normal: &Vector3<f64>, wavelength: Option<f64>) -> Option<Bsdf> {
+        direction
+            .refraction(normal, 1.0, self.refractive_index(wavelength))
+            .map(|direction| Bsdf {
+                direction,
+                signal: Vector3::new(1.0, 1.0, 1.0),
+                diffuse: false,
+            })
+    }
+
+    fn refracted_exit(&self, exited: Vector3<f64>, length: f64) -> Bsdf {
         let opacity = 1.0 - self.transparency;
         let volume = f64::min(opacity * length * length, 1.0);
         let tint = Vector3::new(1.0, 1.0, 1.0).lerp(&self.color, volume);
-        BSDF {
+        Bsdf {
             direction: exited,
             signal: tint,
+            diffuse: false,
         }
     }
 }
 
+impl Anisotropy {
+    // Reflects off a facet picked in proportion to its projected area, giving
+    // the new direction and its weight from the Smith shadowing term.
+    fn reflect(
+        &self,
+        direction: &Vector3<f64>,
+        normal: &Vector3<f64>,
+        tangent: &Vector3<f64>,
+    ) -> Option<(Vector3<f64>, f64)> {
+        let bitangent = normal.cross(tangent);
+        let view = -direction;
+        let incoming = Vector3::new(view.dot(tangent), view.dot(&bitangent), view.dot(normal));
+
+        let facet = self.sample_facet();
+        let outgoing = facet * (2.0 * incoming.dot(&facet)) - incoming;
+        if incoming.z <= 0.0 || outgoing.z <= 0.0 {
+            return None;
+        }
+
+        let shadowing = 1.0 / (1.0 + self.lambda(&incoming) + self.lambda(&outgoing));
+        let weight = shadowing * incoming.dot(&facet).abs() / (incoming.z * facet.z);
+        let direction = tangent * outgoing.x + bitangent * outgoing.y + normal * outgoing.z;
+        Some((direction, weight))
+    }
+
+    // A facet normal in the local frame, where z is the surface normal and x
+    // the tangent, distributed as D(m) cos(θm).
+    fn sample_facet(&self) -> Vector3<f64> {
+        let u = rand::random::<f64>();
+        let v = rand::random::<f64>();
+        let phi = (self.alpha_y * (2.0 * f64::consts::PI * v).sin())
+            .atan2(self.alpha_x * (2.0 * f64::consts::PI * v).cos());
+        let (sin_phi, cos_phi) = phi.sin_cos();
+        let slope = cos_phi * cos_phi / (self.alpha_x * self.alpha_x)
+            + sin_phi * sin_phi / (self.alpha_y * self.alpha_y);
+        let tan2_theta = u / ((1.0 - u) * slope);
+        let cos_theta = 1.0 / (1.0 + tan2_theta).sqrt();
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        Vector3::new(sin_theta * cos_phi, sin_theta * sin_phi, cos_theta)
+    }
+
+    fn lambda(&self, local: &Vector3<f64>) -> f64 {
+        let stretched = (local.x * self.alpha_x).powi(2) + (local.y * self.alpha_y).powi(2);
+        (-1.0 + (1.0 + stretched / (local.z * local.z)).sqrt()) * 0.5
+    }
+}
+
 struct FilteredProbabilityTest {
     r: f64,
     p: f64
@@ -129,7 +648,7 @@ impl FilteredProbabilityTest {
     }
 }
 
-trait Averageable {
+pub trait Averageable {
     fn average(&self) -> f64;
 }
 
@@ -213,7 +732,8 @@ impl Ray for Vector3<f64> {
 #[cfg(test)]
 mod test {
     use super::*;
-    use nalgebra::Vector3;
+    use crate::nodes::Node;
+    use nalgebra::{Point3, Vector3};
 
     #[test]
     fn schilck_is_correct() {
@@ -248,4 +768,139 @@ mod test {
             )
         )
     }
+
+    #[test]
+    fn rough_diffuse_flattens_at_grazing_angles() {
+        let clay = Material::new(
+            Vector3::new(0.8, 0.5, 0.3),
+            1.0,
+            0.0,
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, 0.0),
+            0.0,
+            0.0
+        );
+        let normal = Vector3::new(0.0, 1.0, 0.0);
+        let grazing = Vector3::new(1.0, 0.2, 0.0).normalize();
+        let lambert = clay.diffuse_response(&normal, &grazing, &grazing);
+
+        let rough = clay.with_diffuse_roughness(0.5);
+        let back_scattered = rough.diffuse_response(&normal, &grazing, &grazing);
+        let overhead = rough.diffuse_response(&normal, &normal, &grazing);
+        assert!(back_scattered.x > lambert.x);
+        assert!(overhead.x < lambert.x);
+    }
+
+    #[test]
+    fn anisotropic_highlights_stretch_along_the_tangent() {
+        let brushed = Anisotropy {
+            alpha_x: 0.5,
+            alpha_y: 0.05,
+        };
+        let normal = Vector3::new(0.0, 0.0, 1.0);
+        let tangent = Vector3::new(1.0, 0.0, 0.0);
+        let direction = Vector3::new(0.0, 0.0, -1.0);
+
+        let (along, across) = (0..2000)
+            .filter_map(|_| brushed.reflect(&direction, &normal, &tangent))
+            .fold((0.0, 0.0), |(along, across), (reflected, weight)| {
+                assert!((0.0..=1.0 + 1e-9).contains(&weight));
+                (along + reflected.x.abs(), across + reflected.y.abs())
+            });
+        assert!(along > 5.0 * across);
+    }
+
+    #[test]
+    fn light_which_cant_refract_in_is_reflected() {
+        let thin = Material::new(
+            Vector3::new(0.0, 0.0, 0.0),
+            1.5,
+            1.0,
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, 0.0),
+            0.0,
+            1.0,
+        )
+        .with_dispersion(Dispersion::cauchy(0.8, 0.0));
+        let normal = Vector3::new(0.0, 1.0, 0.0);
+        let tangent = Vector3::new(1.0, 0.0, 0.0);
+        let direction = Vector3::new(0.9, -(1.0f64 - 0.81).sqrt(), 0.0);
+
+        for _ in 0..100 {
+            let bsdf = thin
+                .bsdf(&normal, &tangent, &direction, &Point2::origin(), 1.0, Some(550.0))
+                .unwrap();
+            assert!(bsdf.direction.y > 0.0);
+        }
+    }
+
+    #[test]
+    fn layers_scatter_in_proportion_to_their_reflectance() {
+        let black = Material::new(
+            Vector3::new(0.0, 0.0, 0.0),
+            1.0,
+            0.0,
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, 0.0),
+            0.0,
+            0.0,
+        );
+        let normal = Vector3::new(0.0, 1.0, 0.0);
+        let grazing = Vector3::new(0.6, -0.8, 0.0);
+        let odds = |material: &Material| {
+            let hits = (0..20000).filter(|_| material.layer(&normal, &grazing).is_some()).count();
+            hits as f64 / 20000.0
+        };
+
+        // A coat of index 1.5 reflects 4% head on, rising towards grazing.
+        let coated = black.clone().with_clearcoat(1.5, 1.0);
+        let expected = 0.04 + 0.96 * 0.2f64.powi(5);
+        assert!((odds(&coated) - expected).abs() < 0.01);
+        let coat = (0..1000).find_map(|_| coated.layer(&normal, &-normal)).unwrap();
+        assert!((coat.direction - normal).norm() < 1e-9);
+
+        // Sheen reflects like a white diffuse surface tinted by its colour.
+        let velvet = black.with_sheen(Vector3::new(1.0, 0.5, 0.5));
+        let sheen = (0..20000).find_map(|_| velvet.layer(&normal, &grazing)).unwrap();
+        let white = Material::new(
+            Vector3::new(1.0, 1.0, 1.0),
+            1.0,
+            0.0,
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, 0.0),
+            0.0,
+            0.0,
+        );
+        let diffuse = white.diffused(&normal, &-grazing).signal;
+        assert!((sheen.signal.average() - diffuse.average()).abs() < 1e-9);
+        assert!((sheen.signal.x - 2.0 * sheen.signal.y).abs() < 1e-9);
+    }
+
+    #[test]
+    fn inputs_are_evaluated_in_the_graph_they_were_bound_with() {
+        let mut colours = NodeGraph::new();
+        let red = colours.add(Node::Constant(Vector3::new(1.0, 0.0, 0.0))).unwrap();
+        let mut levels = NodeGraph::new();
+        let half = levels.add(Node::Constant(Vector3::new(0.5, 0.5, 0.5))).unwrap();
+
+        let material = Material::new(
+            Vector3::new(0.0, 0.0, 0.0),
+            1.0,
+            0.0,
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.04, 0.04, 0.04),
+            0.0,
+            0.0,
+        )
+        .with_input(Input::Color, Rc::new(colours), red)
+        .with_input(Input::Gloss, Rc::new(levels), half);
+        let shaded = material.shade(&ShadingPoint {
+            position: Point3::origin(),
+            normal: Vector3::new(0.0, 1.0, 0.0),
+            view: Vector3::new(0.0, 1.0, 0.0),
+            uv: Point2::origin(),
+        });
+        assert_eq!(shaded.color, Vector3::new(1.0, 0.0, 0.0));
+        assert_eq!(shaded.gloss, 0.5);
+    }
 }
@@ -0,0 +1,250 @@
+use crate::shape::orthonormal_basis;
+use nalgebra::Vector3;
+use std::convert::TryInto;
+use std::f64;
+
+// Resolution of the MERL tables in half angle, difference angle and
+// difference azimuth. Reciprocity halves the azimuths stored.
+const THETA_HALF: usize = 90;
+const THETA_DIFF: usize = 90;
+const PHI_DIFF: usize = 180;
+const ENTRIES: usize = THETA_HALF * THETA_DIFF * PHI_DIFF;
+
+// The tables are stored scaled differently in each channel.
+const SCALE: [f64; 3] = [1.0 / 1500.0, 1.15 / 1500.0, 1.66 / 1500.0];
+
+// Resolution of the tables used to importance sample the measurements: the
+// incoming elevation, then the outgoing elevation and azimuth.
+const SAMPLED_INCOMING: usize = 16;
+const SAMPLED_THETA: usize = 32;
+const SAMPLED_PHI: usize = 64;
+
+// An isotropic reflectance function measured from a real material, stored
+// in Rusinkiewicz's half and difference angles.
+pub struct MeasuredBrdf {
+    data: Vec<[f32; 3]>,
+    // Cumulative weights over the outgoing cells for each incoming
+    // elevation.
+    cdfs: Vec<Vec<f64>>,
+}
+
+impl MeasuredBrdf {
+    // Reads a MERL .binary file: three little endian i32 dimensions followed
+    // by every red, then green, then blue value as a little endian f64.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < 12 {
+            return None;
+        }
+
+        let int = |index: usize| i32::from_le_bytes(bytes[index..index + 4].try_into().unwrap());
+        let count = int(0) as i64 * int(4) as i64 * int(8) as i64;
+        if count != ENTRIES as i64 || bytes.len() != 12 + ENTRIES * 3 * 8 {
+            return None;
+        }
+
+        let float = |index: usize| f64::from_le_bytes(bytes[index..index + 8].try_into().unwrap());
+        let data = (0..ENTRIES)
+            .map(|i| {
+                let channel = |c: usize| (float(12 + (c * ENTRIES + i) * 8) * SCALE[c]).max(0.0) as f32;
+                [channel(0), channel(1), channel(2)]
+            })
+            .collect();
+
+        Some(MeasuredBrdf::from_table(data))
+    }
+
+    fn from_table(data: Vec<[f32; 3]>) -> Self {
+        let mut brdf = MeasuredBrdf { data, cdfs: vec![] };
+        brdf.cdfs = (0..SAMPLED_INCOMING).map(|bin| brdf.cdf(bin)).collect();
+        brdf
+    }
+
+    // Cumulative weights of the outgoing cells for light seen from the
+    // centre of an incoming elevation bin, in proportion to the average
+    // reflected across the cell. A little is spread evenly so that no
+    // direction is impossible to sample.
+    fn cdf(&self, bin: usize) -> Vec<f64> {
+        let theta = (bin as f64 + 0.5) / SAMPLED_INCOMING as f64 * f64::consts::FRAC_PI_2;
+        let view = Vector3::new(theta.sin(), 0.0, theta.cos());
+
+        let weights: Vec<f64> = (0..SAMPLED_THETA * SAMPLED_PHI)
+            .map(|cell| {
+                let (theta, phi) = cell_centre(cell);
+                let direction = from_spherical(theta, phi);
+                let value = self.local(&view, &direction);
+                (value.x + value.y + value.z) / 3.0 * theta.cos() * theta.sin()
+            })
+            .collect();
+
+        let floor = weights.iter().sum::<f64>() / weights.len() as f64 * 0.05 + 1e-9;
+        weights
+            .iter()
+            .scan(0.0, |total, weight| {
+                *total += weight + floor;
+                Some(*total)
+            })
+            .collect()
+    }
+
+    // Reflectance in each channel, per steradian, for light arriving from
+    // `direction` and leaving towards `view`.
+    pub fn evaluate(&self, normal: &Vector3<f64>, view: &Vector3<f64>, direction: &Vector3<f64>) -> Vector3<f64> {
+        let (tangent, bitangent) = frame(normal, view);
+        let to_local = |v: &Vector3<f64>| Vector3::new(v.dot(&tangent), v.dot(&bitangent), v.dot(normal));
+        self.local(&to_local(view), &to_local(direction))
+    }
+
+    // Picks the incoming direction for light leaving towards `view` from the
+    // tables, giving the direction and its weight: the reflectance times the
+    // cosine over the probability density.
+    pub fn sample(&self, normal: &Vector3<f64>, view: &Vector3<f64>) -> Option<(Vector3<f64>, Vector3<f64>)> {
+        let (tangent, bitangent) = frame(normal, view);
+        let local_view = Vector3::new(view.dot(&tangent), view.dot(&bitangent), view.dot(normal));
+        if local_view.z <= 0.0 {
+            return None;
+        }
+
+        let cdf = &self.cdfs[incoming_bin(&local_view)];
+        let target = rand::random::<f64>() * cdf[cdf.len() - 1];
+        let cell = cdf.partition_point(|total| *total < target).min(cdf.len() - 1);
+
+        let (row, column) = (cell / SAMPLED_PHI, cell % SAMPLED_PHI);
+        let theta = (row as f64 + rand::random::<f64>()) * theta_step();
+        let phi = (column as f64 + rand::random::<f64>()) * phi_step();
+        let local = from_spherical(theta, phi);
+
+        let pdf = cell_pdf(cdf, cell, theta);
+        if pdf <= 0.0 {
+            return None;
+        }
+        let signal = self.local(&local_view, &local) * (local.z / pdf);
+        let direction = tangent * local.x + bitangent * local.y + normal * local.z;
+        Some((direction, signal))
+    }
+
+    // Solid angle density with which `sample` picks `direction`.
+    pub fn pdf(&self, normal: &Vector3<f64>, view: &Vector3<f64>, direction: &Vector3<f64>) -> f64 {
+        let (tangent, bitangent) = frame(normal, view);
+        let local_view = Vector3::new(view.dot(&tangent), view.dot(&bitangent), view.dot(normal));
+        let local = Vector3::new(direction.dot(&tangent), direction.dot(&bitangent), direction.dot(normal));
+        if local_view.z <= 0.0 || local.z <= 0.0 {
+            return 0.0;
+        }
+
+        let theta = local.z.min(1.0).acos();
+        let phi = local.y.atan2(local.x).rem_euclid(2.0 * f64::consts::PI);
+        let row = ((theta / theta_step()) as usize).min(SAMPLED_THETA - 1);
+        let column = ((phi / phi_step()) as usize).min(SAMPLED_PHI - 1);
+        cell_pdf(&self.cdfs[incoming_bin(&local_view)], row * SAMPLED_PHI + column, theta)
+    }
+
+    // Looks up the table with both directions given in the surface's local
+    // frame, where z is the normal.
+    fn local(&self, view: &Vector3<f64>, direction: &Vector3<f64>) -> Vector3<f64> {
+        if view.z <= 0.0 || direction.z <= 0.0 {
+            return Vector3::zeros();
+        }
+
+        let half = (view + direction).normalize();
+        let theta_half = half.z.min(1.0).acos();
+        let phi_half = half.y.atan2(half.x);
+        let difference = rotate_y(&rotate_z(direction, -phi_half), -theta_half);
+        let theta_diff = difference.z.clamp(-1.0, 1.0).acos();
+        let mut phi_diff = difference.y.atan2(difference.x);
+        if phi_diff < 0.0 {
+            phi_diff += f64::consts::PI;
+        }
+
+        // Half angles are stored more densely near the normal, where the
+        // highlights are.
+        let half_index = ((theta_half / f64::consts::FRAC_PI_2).sqrt() * THETA_HALF as f64) as usize;
+        let diff_index = (theta_diff / f64::consts::FRAC_PI_2 * THETA_DIFF as f64) as usize;
+        let phi_index = (phi_diff / f64::consts::PI * PHI_DIFF as f64) as usize;
+        let index = phi_index.min(PHI_DIFF - 1)
+            + diff_index.min(THETA_DIFF - 1) * PHI_DIFF
+            + half_index.min(THETA_HALF - 1) * PHI_DIFF * THETA_DIFF;
+
+        let [r, g, b] = self.data[index];
+        Vector3::new(f64::from(r), f64::from(g), f64::from(b))
+    }
+}
+
+// A frame around the normal whose tangent points along the view, so that the
+// view's azimuth is always zero.
+fn frame(normal: &Vector3<f64>, view: &Vector3<f64>) -> (Vector3<f64>, Vector3<f64>) {
+    let along = view - normal * normal.dot(view);
+    let tangent = if along.norm() > 1e-6 {
+        along.normalize()
+    } else {
+        orthonormal_basis(normal).0
+    };
+    (tangent, normal.cross(&tangent))
+}
+
+fn incoming_bin(view: &Vector3<f64>) -> usize {
+    let theta = view.z.min(1.0).acos();
+    ((theta / f64::consts::FRAC_PI_2 * SAMPLED_INCOMING as f64) as usize).min(SAMPLED_INCOMING - 1)
+}
+
+fn cell_pdf(cdf: &[f64], cell: usize, theta: f64) -> f64 {
+    let below = if cell == 0 { 0.0 } else { cdf[cell - 1] };
+    let probability = (cdf[cell] - below) / cdf[cdf.len() - 1];
+    probability / (theta_step() * phi_step() * theta.sin().max(1e-6))
+}
+
+fn cell_centre(cell: usize) -> (f64, f64) {
+    let (row, column) = (cell / SAMPLED_PHI, cell % SAMPLED_PHI);
+    ((row as f64 + 0.5) * theta_step(), (column as f64 + 0.5) * phi_step())
+}
+
+fn theta_step() -> f64 {
+    f64::consts::FRAC_PI_2 / SAMPLED_THETA as f64
+}
+
+fn phi_step() -> f64 {
+    2.0 * f64::consts::PI / SAMPLED_PHI as f64
+}
+
+fn from_spherical(theta: f64, phi: f64) -> Vector3<f64> {
+    Vector3::new(theta.sin() * phi.cos(), theta.sin() * phi.sin(), theta.cos())
+}
+
+fn rotate_z(vector: &Vector3<f64>, angle: f64) -> Vector3<f64> {
+    let (sin, cos) = angle.sin_cos();
+    Vector3::new(vector.x * cos - vector.y * sin, vector.x * sin + vector.y * cos, vector.z)
+}
+
+fn rotate_y(vector: &Vector3<f64>, angle: f64) -> Vector3<f64> {
+    let (sin, cos) = angle.sin_cos();
+    Vector3::new(vector.x * cos + vector.z * sin, vector.y, -vector.x * sin + vector.z * cos)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn sampling_a_flat_table_conserves_its_albedo() {
+        let mut bytes = vec![];
+        for dimension in &[THETA_HALF, THETA_DIFF, PHI_DIFF] {
+            bytes.extend_from_slice(&(*dimension as i32).to_le_bytes());
+        }
+        for scale in &SCALE {
+            let value = 0.5 / f64::consts::PI / scale;
+            for _ in 0..ENTRIES {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        let brdf = MeasuredBrdf::from_bytes(&bytes).unwrap();
+        assert!(MeasuredBrdf::from_bytes(&bytes[..100]).is_none());
+
+        let normal = Vector3::new(0.0, 1.0, 0.0);
+        let view = Vector3::new(0.6, 0.8, 0.0);
+        let samples = 20000;
+        let albedo = (0..samples)
+            .filter_map(|_| brdf.sample(&normal, &view))
+            .fold(Vector3::zeros(), |sum, (_, signal)| sum + signal)
+            / samples as f64;
+        assert!((albedo - Vector3::new(0.5, 0.5, 0.5)).amax() < 0.02);
+    }
+}
@@ -0,0 +1,315 @@
+use crate::material::Averageable;
+use crate::ray::Ray;
+use crate::shape::orthonormal_basis;
+use crate::volume::{Noise, VoxelGrid};
+use nalgebra::{Point3, Vector3};
+use std::f64;
+use std::rc::Rc;
+
+pub enum MediumSample {
+    Scatter { distance: f64, weight: Vector3<f64> },
+    Pass { weight: Vector3<f64> },
+}
+
+// Scales the absorption and scattering of a medium from point to point.
+#[derive(Clone)]
+pub enum Density {
+    Uniform,
+    Grid(Rc<VoxelGrid>),
+    Noise(Noise),
+}
+
+impl Density {
+    fn at(&self, point: &Point3<f64>) -> f64 {
+        match self {
+            Density::Uniform => 1.0,
+            Density::Grid(grid) => grid.density(point),
+            Density::Noise(noise) => noise.density(point),
+        }
+    }
+
+    fn max(&self) -> f64 {
+        match self {
+            Density::Uniform | Density::Noise(_) => 1.0,
+            Density::Grid(grid) => grid.max_density(),
+        }
+    }
+
+    // The stretch of the ray up to `max_distance` which may hold any density,
+    // or nothing if none of it does. Grids are empty outside their bounds,
+    // which keeps tracking finite along rays leaving the scene.
+    fn extent(&self, ray: &Ray, max_distance: f64) -> Option<(f64, f64)> {
+        let (min, max) = match self {
+            Density::Grid(grid) => grid.bounds(),
+            _ => return Some((0.0, max_distance)),
+        };
+
+        let mut near = 0.0;
+        let mut far = max_distance;
+        for axis in 0..3 {
+            let inverse = 1.0 / ray.direction[axis];
+            let mut t0 = (min[axis] - ray.origin[axis]) * inverse;
+            let mut t1 = (max[axis] - ray.origin[axis]) * inverse;
+            if t0 > t1 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            near = f64::max(near, t0);
+            far = f64::min(far, t1);
+        }
+
+        if near < far {
+            Some((near, far))
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Medium {
+    absorption: Vector3<f64>,
+    scattering: Vector3<f64>,
+    anisotropy: f64,
+    density: Density,
+}
+
+impl Medium {
+    pub fn new(absorption: Vector3<f64>, scattering: Vector3<f64>, anisotropy: f64) -> Self {
+        Medium {
+            absorption,
+            scattering,
+            anisotropy,
+            density: Density::Uniform,
+        }
+    }
+
+    // The absorption and scattering given to `new` then apply where the
+    // density is one.
+    pub fn with_density(mut self, density: Density) -> Self {
+        self.density = density;
+        self
+    }
+
+    fn extinction(&self) -> Vector3<f64> {
+        self.absorption + self.scattering
+    }
+
+    // An extinction at least as high as anywhere in the medium, in every
+    // channel.
+    fn majorant(&self) -> f64 {
+        self.extinction().max() * self.density.max()
+    }
+
+    // Fraction of light which makes it `distance` along the ray. Varying
+    // densities are estimated with ratio tracking.
+    pub fn transmittance(&self, ray: &Ray, distance: f64) -> Vector3<f64> {
+        if let Density::Uniform = self.density {
+            return (-self.extinction() * distance).map(f64::exp);
+        }
+
+        let mut transmittance = Vector3::new(1.0, 1.0, 1.0);
+        let majorant = self.majorant();
+        let (mut t, end) = match self.density.extent(ray, distance) {
+            Some(extent) if majorant > 0.0 => extent,
+            _ => return transmittance,
+        };
+
+        loop {
+            t -= (1.0 - rand::random::<f64>()).ln() / majorant;
+            if t >= end {
+                return transmittance;
+            }
+
+            let extinction = self.extinction() * self.density.at(&(ray.origin + ray.direction * t));
+            transmittance.component_mul_assign(&extinction.map(|e| 1.0 - e / majorant));
+        }
+    }
+
+    pub fn sample_distance(&self, ray: &Ray, max_distance: f64) -> MediumSample {
+        match self.density {
+            Density::Uniform => self.sample_uniform_distance(max_distance),
+            _ => self.track_distance(ray, max_distance),
+        }
+    }
+
+    // Samples a free flight distance using the extinction of one randomly
+    // chosen channel, weighting by the average density over all channels.
+    fn sample_uniform_distance(&self, max_distance: f64) -> MediumSample {
+        let extinction = self.extinction();
+        let channel = ((rand::random::<f64>() * 3.0) as usize).min(2);
+        let distance = -(1.0 - rand::random::<f64>()).ln() / extinction[channel];
+
+        if distance < max_distance {
+            let transmittance = (-extinction * distance).map(f64::exp);
+            let pdf = extinction.component_mul(&transmittance).average();
+            MediumSample::Scatter {
+                distance,
+                weight: self.scattering.component_mul(&transmittance) / pdf,
+            }
+        } else {
+            let transmittance = (-extinction * max_distance).map(f64::exp);
+            let pdf = transmittance.average();
+            MediumSample::Pass {
+                weight: if pdf > 0.0 {
+                    transmittance / pdf
+                } else {
+                    Vector3::zeros()
+                },
+            }
+        }
+    }
+
+    // Delta tracking against the majorant. Each tentative collision either
+    // scatters or carries on as a null collision, chosen in proportion to the
+    // average of the channels, with absorption folded into the weights.
+    fn track_distance(&self, ray: &Ray, max_distance: f64) -> MediumSample {
+        let mut weight = Vector3::new(1.0, 1.0, 1.0);
+        let majorant = self.majorant();
+        let (mut t, end) = match self.density.extent(ray, max_distance) {
+            Some(extent) if majorant > 0.0 => extent,
+            _ => return MediumSample::Pass { weight },
+        };
+
+        loop {
+            t -= (1.0 - rand::random::<f64>()).ln() / majorant;
+            if t >= end {
+                return MediumSample::Pass { weight };
+            }
+
+            let density = self.density.at(&(ray.origin + ray.direction * t));
+            let scattering = self.scattering * density;
+            let null = Vector3::repeat(majorant) - self.extinction() * density;
+
+            let scatter_chance = scattering.average() / majorant;
+            if rand::random::<f64>() < scatter_chance {
+                return MediumSample::Scatter {
+                    distance: t,
+                    weight: weight.component_mul(&scattering) / (majorant * scatter_chance),
+                };
+            }
+            weight.component_mul_assign(&(null / (majorant * (1.0 - scatter_chance))));
+            if weight.max() == 0.0 {
+                return MediumSample::Pass { weight };
+            }
+        }
+    }
+
+    // Henyey-Greenstein phase function, where `cos_theta` is measured between
+    // the incoming direction of travel and the scattered direction.
+    pub fn phase(&self, cos_theta: f64) -> f64 {
+        let g = self.anisotropy;
+        let denominator = 1.0 + g * g - 2.0 * g * cos_theta;
+        (1.0 - g * g) / (4.0 * f64::consts::PI * denominator * denominator.sqrt())
+    }
+
+    pub fn sample_phase(&self, direction: &Vector3<f64>) -> Vector3<f64> {
+        let g = self.anisotropy;
+        let u = rand::random::<f64>();
+        let cos_theta = if g.abs() < 1e-3 {
+            1.0 - 2.0 * u
+        } else {
+            let square = (1.0 - g * g) / (1.0 - g + 2.0 * g * u);
+            (1.0 + g * g - square * square) / (2.0 * g)
+        };
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * f64::consts::PI * rand::random::<f64>();
+
+        let (tangent, bitangent) = orthonormal_basis(direction);
+        tangent * (sin_theta * phi.cos()) + bitangent * (sin_theta * phi.sin()) + direction * cos_theta
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn phase_function_integrates_to_one() {
+        let medium = Medium::new(Vector3::zeros(), Vector3::new(1.0, 1.0, 1.0), 0.7);
+        let steps = 10000;
+        let integral: f64 = (0..steps)
+            .map(|i| {
+                let cos_theta = -1.0 + 2.0 * (i as f64 + 0.5) / steps as f64;
+                medium.phase(cos_theta) * 2.0 * f64::consts::PI * 2.0 / steps as f64
+            })
+            .sum();
+        assert!((integral - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn free_flights_follow_the_extinction() {
+        let ray = Ray {
+            origin: Point3::origin(),
+            direction: Vector3::new(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+        let samples = 200_000;
+
+        // With nothing to stop them every flight scatters, on average one
+        // mean free path in.
+        let grey = Medium::new(Vector3::repeat(0.5), Vector3::repeat(1.5), 0.0);
+        let mut total = 0.0;
+        for _ in 0..samples {
+            match grey.sample_distance(&ray, f64::INFINITY) {
+                MediumSample::Scatter { distance, weight } => {
+                    total += distance;
+                    assert!((weight - Vector3::repeat(0.75)).norm() < 1e-9);
+                }
+                MediumSample::Pass { .. } => panic!("passed through an endless medium"),
+            }
+        }
+        assert!((total / samples as f64 - 0.5).abs() < 0.01);
+
+        // Flights sampled by one channel still let each channel through as
+        // Beer-Lambert says.
+        let coloured = Medium::new(Vector3::zeros(), Vector3::new(1.0, 2.0, 4.0), 0.0);
+        let mut passed = Vector3::zeros();
+        for _ in 0..samples {
+            if let MediumSample::Pass { weight } = coloured.sample_distance(&ray, 0.5) {
+                passed += weight;
+            }
+        }
+        let expected = Vector3::new(-0.5f64, -1.0, -2.0).map(f64::exp);
+        assert!((passed / samples as f64 - expected).amax() < 0.01);
+        assert!((coloured.transmittance(&ray, 0.5) - expected).amax() < 1e-12);
+    }
+
+    #[test]
+    fn tracking_through_a_grid_stops_at_its_bounds() {
+        // A uniform block two units deep, seen from outside along a ray which
+        // never ends.
+        let grid = VoxelGrid::new(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0), [1, 1, 1], vec![2.0]);
+        let density = Density::Grid(Rc::new(grid));
+        let ray = Ray {
+            origin: Point3::new(0.0, 0.0, 5.0),
+            direction: Vector3::new(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+        let samples = 100_000;
+
+        let grey = Medium::new(Vector3::zeros(), Vector3::repeat(0.5), 0.0).with_density(density.clone());
+        let mut passed = 0.0;
+        for _ in 0..samples {
+            match grey.sample_distance(&ray, f64::INFINITY) {
+                MediumSample::Scatter { distance, .. } => assert!((4.0..=6.0).contains(&distance)),
+                MediumSample::Pass { weight } => passed += weight.average(),
+            }
+        }
+        assert!((passed / samples as f64 - (-2f64).exp()).abs() < 0.01);
+
+        // Ratio tracking, where the dimmer channel sees null collisions.
+        let coloured = Medium::new(Vector3::zeros(), Vector3::new(0.25, 0.5, 0.5), 0.0).with_density(density);
+        let transmittance = (0..samples)
+            .map(|_| coloured.transmittance(&ray, f64::INFINITY))
+            .fold(Vector3::zeros(), |total, sample| total + sample)
+            / samples as f64;
+        let expected = Vector3::new(-1f64, -2.0, -2.0).map(f64::exp);
+        assert!((transmittance - expected).amax() < 0.01);
+
+        let beside = Ray {
+            origin: Point3::new(3.0, 0.0, 5.0),
+            ..ray
+        };
+        assert_eq!(coloured.transmittance(&beside, f64::INFINITY), Vector3::new(1.0, 1.0, 1.0));
+    }
+}
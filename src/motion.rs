@@ -0,0 +1,140 @@
+use crate::camera::{Camera, View};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::shape::{Shape, Span, Surface, SurfaceSample};
+use nalgebra::{Point3, Vector3};
+
+// A shape sliding along at a constant velocity, so that it streaks across
+// the image while the shutter is open. It stands where it was described at
+// time zero.
+pub struct Moving {
+    shape: Box<dyn Shape>,
+    velocity: Vector3<f64>,
+}
+
+impl Moving {
+    pub fn new(shape: Box<dyn Shape>, velocity: Vector3<f64>) -> Self {
+        Moving { shape, velocity }
+    }
+
+    // The ray as seen by the shape standing still at time zero.
+    fn relative(&self, ray: &Ray) -> Ray {
+        Ray {
+            origin: ray.origin - self.displacement(ray.time),
+            direction: ray.direction,
+            time: ray.time,
+        }
+    }
+}
+
+impl Shape for Moving {
+    fn intersection_distance(&self, ray: &Ray) -> f64 {
+        self.shape.intersection_distance(&self.relative(ray))
+    }
+
+    fn surface(&self, ray: &Ray, distance: f64) -> Surface {
+        self.shape.surface(&self.relative(ray), distance)
+    }
+
+    fn material(&self) -> &Material {
+        self.shape.material()
+    }
+
+    fn tangent(&self, point: &Point3<f64>, normal: &Vector3<f64>) -> Vector3<f64> {
+        self.shape.tangent(point, normal)
+    }
+
+    fn displacement(&self, time: f64) -> Vector3<f64> {
+        self.velocity * time
+    }
+
+    fn area(&self) -> f64 {
+        self.shape.area()
+    }
+
+    fn sample_surface(&self) -> Option<SurfaceSample> {
+        self.shape.sample_surface()
+    }
+
+    fn spans(&self, ray: &Ray) -> Vec<Span> {
+        self.shape.spans(&self.relative(ray))
+    }
+}
+
+// Holds the shutter of a camera open from one time to another, sending each
+// ray at a random moment in between. The camera may also move over the
+// interval, carrying its rays with it.
+pub struct Shutter<C> {
+    camera: C,
+    open: f64,
+    close: f64,
+    motion: Option<(View, View)>,
+}
+
+impl<C: Camera> Shutter<C> {
+    pub fn new(camera: C, open: f64, close: f64) -> Self {
+        Shutter {
+            camera,
+            open,
+            close,
+            motion: None,
+        }
+    }
+
+    // Moves the camera from `start`, the view it was made with, to `end` as
+    // the shutter goes from open to closed.
+    pub fn with_motion(mut self, start: View, end: View) -> Self {
+        self.motion = Some((start, end));
+        self
+    }
+}
+
+impl<C: Camera> Camera for Shutter<C> {
+    fn focus_on(&mut self, point: &Point3<f64>) -> Option<f64> {
+        self.camera.focus_on(point)
+    }
+
+    fn ray(&self, x: usize, y: usize, width: usize, height: usize) -> Option<Ray> {
+        let mut ray = self.camera.ray(x, y, width, height)?;
+        let fraction = rand::random::<f64>();
+        ray.time = self.open + (self.close - self.open) * fraction;
+
+        if let Some((start, end)) = self.motion {
+            let view = start.lerp(&end, fraction);
+            let origin = start.to_local(&(ray.origin - start.position));
+            ray.origin = view.position + view.to_world(&origin);
+            ray.direction = view.to_world(&start.to_local(&ray.direction));
+        }
+        Some(ray)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::sphere::Sphere;
+
+    #[test]
+    fn moving_shapes_are_hit_where_they_are_at_the_time_of_the_ray() {
+        let material = Material::new(
+            Vector3::new(0.5, 0.5, 0.5),
+            1.0,
+            0.0,
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.04, 0.04, 0.04),
+            0.0,
+            0.0,
+        );
+        let sphere = Sphere::new(Point3::new(0.0, 0.0, -5.0), 1.0, material);
+        let moving = Moving::new(Box::new(sphere), Vector3::new(4.0, 0.0, 0.0));
+
+        let ray = |time: f64| Ray {
+            origin: Point3::new(2.0, 0.0, 0.0),
+            direction: Vector3::new(0.0, 0.0, -1.0),
+            time,
+        };
+        assert!(moving.intersection_distance(&ray(0.0)).is_infinite());
+        assert!((moving.intersection_distance(&ray(0.5)) - 4.0).abs() < 1e-9);
+        assert!((moving.surface(&ray(0.5), 4.0).normal - Vector3::new(0.0, 0.0, 1.0)).norm() < 1e-9);
+    }
+}
@@ -0,0 +1,139 @@
+use crate::texture::Texture;
+use nalgebra::{Point2, Point3, Vector3};
+
+// What a shader node graph can see of the surface it is evaluated on.
+pub struct ShadingPoint {
+    pub position: Point3<f64>,
+    pub normal: Vector3<f64>,
+    // Direction back towards where the ray came from.
+    pub view: Vector3<f64>,
+    pub uv: Point2<f64>,
+}
+
+// A single step of a shader. Every node produces a colour; greyscale values
+// are stored in all three channels. Inputs name earlier nodes in the graph
+// by index.
+pub enum Node {
+    Constant(Vector3<f64>),
+    Texture(Texture),
+    // Blends from the first input to the second by the third.
+    Mix(usize, usize, usize),
+    Multiply(usize, usize),
+    Add(usize, usize),
+    // Maps the input linearly from one range to another.
+    Remap(usize, f64, f64, f64, f64),
+    // Schlick's reflectance for a dielectric of the given refractive index.
+    Fresnel(f64),
+    // How far the surface faces away from the viewer, pushed towards zero or
+    // one by the blend.
+    LayerWeight(f64),
+    // Looks up the input in a colour ramp of sorted stops.
+    Ramp(usize, Vec<(f64, Vector3<f64>)>),
+}
+
+// Nodes shared between all of the materials in a scene. Each node only takes
+// inputs from nodes added before it, so the graph can't hold cycles.
+#[derive(Default)]
+pub struct NodeGraph {
+    nodes: Vec<Node>,
+}
+
+impl NodeGraph {
+    pub fn new() -> Self {
+        NodeGraph { nodes: vec![] }
+    }
+
+    // Adds a node, giving the index other nodes refer to it by, or nothing if
+    // it takes an input which hasn't been added yet.
+    pub fn add(&mut self, node: Node) -> Option<usize> {
+        let inputs = match &node {
+            Node::Mix(a, b, factor) => vec![*a, *b, *factor],
+            Node::Multiply(a, b) | Node::Add(a, b) => vec![*a, *b],
+            Node::Remap(input, ..) | Node::Ramp(input, _) => vec![*input],
+            _ => vec![],
+        };
+        if inputs.iter().any(|input| *input >= self.nodes.len()) {
+            return None;
+        }
+
+        self.nodes.push(node);
+        Some(self.nodes.len() - 1)
+    }
+
+    pub fn evaluate(&self, node: usize, point: &ShadingPoint) -> Vector3<f64> {
+        let grey = |value: f64| Vector3::new(value, value, value);
+        let scalar = |input: usize| average(&self.evaluate(input, point));
+
+        match &self.nodes[node] {
+            Node::Constant(value) => *value,
+            Node::Texture(texture) => grey(texture.value(&point.uv)),
+            Node::Mix(a, b, factor) => {
+                self.evaluate(*a, point).lerp(&self.evaluate(*b, point), scalar(*factor))
+            }
+            Node::Multiply(a, b) => self.evaluate(*a, point).component_mul(&self.evaluate(*b, point)),
+            Node::Add(a, b) => self.evaluate(*a, point) + self.evaluate(*b, point),
+            Node::Remap(input, from_low, from_high, to_low, to_high) => {
+                self.evaluate(*input, point).map(|value| {
+                    let t = (value - from_low) / (from_high - from_low);
+                    to_low + (to_high - to_low) * t
+                })
+            }
+            Node::Fresnel(index) => {
+                let root = (index - 1.0) / (index + 1.0);
+                let normal_reflectance = root * root;
+                let cos = point.normal.dot(&point.view).abs().min(1.0);
+                grey(normal_reflectance + (1.0 - normal_reflectance) * (1.0 - cos).powi(5))
+            }
+            Node::LayerWeight(blend) => {
+                let facing = 1.0 - point.normal.dot(&point.view).abs().min(1.0);
+                let blend = blend.clamp(1e-3, 1.0 - 1e-3);
+                grey(facing.powf(blend.ln() / 0.5f64.ln()))
+            }
+            Node::Ramp(input, stops) => ramp(stops, scalar(*input)),
+        }
+    }
+}
+
+fn average(value: &Vector3<f64>) -> f64 {
+    (value.x + value.y + value.z) / 3.0
+}
+
+fn ramp(stops: &[(f64, Vector3<f64>)], position: f64) -> Vector3<f64> {
+    match stops.iter().position(|(stop, _)| *stop > position) {
+        None => stops.last().map_or(Vector3::zeros(), |(_, colour)| *colour),
+        Some(0) => stops[0].1,
+        Some(next) => {
+            let (start, from) = stops[next - 1];
+            let (end, to) = stops[next];
+            from.lerp(&to, (position - start) / (end - start))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn ramps_blend_between_stops() {
+        let mut graph = NodeGraph::new();
+        let gradient = graph.add(Node::Texture(Texture::gradient(0.0, 1.0))).unwrap();
+        let ramp = graph
+            .add(Node::Ramp(
+                gradient,
+                vec![(0.25, Vector3::new(1.0, 0.0, 0.0)), (0.75, Vector3::new(0.0, 0.0, 1.0))],
+            ))
+            .unwrap();
+        assert!(graph.add(Node::Multiply(ramp, 7)).is_none());
+
+        let at = |v: f64| ShadingPoint {
+            position: Point3::origin(),
+            normal: Vector3::new(0.0, 1.0, 0.0),
+            view: Vector3::new(0.0, 1.0, 0.0),
+            uv: Point2::new(0.0, v),
+        };
+        assert_eq!(graph.evaluate(ramp, &at(0.0)), Vector3::new(1.0, 0.0, 0.0));
+        assert_eq!(graph.evaluate(ramp, &at(0.5)), Vector3::new(0.5, 0.0, 0.5));
+        assert_eq!(graph.evaluate(ramp, &at(1.0)), Vector3::new(0.0, 0.0, 1.0));
+    }
+}
@@ -0,0 +1,31 @@
+use crate::camera::{image_point, Camera, View};
+use crate::ray::Ray;
+use nalgebra::Vector3;
+
+// Parallel rays from a rectangle facing the view, so that sizes don't shrink
+// with distance, as in architectural elevations.
+pub struct Orthographic {
+    view: View,
+    height: f64,
+}
+
+impl Orthographic {
+    // `height` is the size of the scene seen from the top to the bottom of
+    // the image.
+    pub fn new(view: View, height: f64) -> Self {
+        Orthographic { view, height }
+    }
+}
+
+impl Camera for Orthographic {
+    fn ray(&self, x: usize, y: usize, width: usize, height: usize) -> Option<Ray> {
+        let aspect = width as f64 / height as f64;
+        let (u, v) = image_point(x, y, width, height);
+        let offset = Vector3::new((u - 0.5) * aspect, 0.5 - v, 0.0) * self.height;
+        Some(Ray {
+            origin: self.view.position + self.view.to_world(&offset),
+            direction: self.view.forward(),
+            time: 0.0,
+        })
+    }
+}
@@ -0,0 +1,98 @@
+use crate::camera::{image_point, Camera, View};
+use crate::ray::Ray;
+use nalgebra::Vector3;
+use std::f64;
+
+// Every direction around the camera, with longitude across the image and
+// latitude down it. The view direction is in the middle.
+pub struct Equirectangular {
+    view: View,
+}
+
+impl Equirectangular {
+    pub fn new(view: View) -> Self {
+        Equirectangular { view }
+    }
+}
+
+impl Camera for Equirectangular {
+    fn ray(&self, x: usize, y: usize, width: usize, height: usize) -> Option<Ray> {
+        let (u, v) = image_point(x, y, width, height);
+        Some(Ray {
+            origin: self.view.position,
+            direction: self.view.to_world(&equirectangular_direction(u, v)),
+            time: 0.0,
+        })
+    }
+}
+
+// Direction in camera space for a point on an equirectangular image.
+pub fn equirectangular_direction(u: f64, v: f64) -> Vector3<f64> {
+    let longitude = (u - 0.5) * 2.0 * f64::consts::PI;
+    let latitude = (0.5 - v) * f64::consts::PI;
+    Vector3::new(
+        longitude.sin() * latitude.cos(),
+        latitude.sin(),
+        -longitude.cos() * latitude.cos(),
+    )
+}
+
+// The six faces of a cube around the camera, laid out in a grid of three
+// across and two down: +x, -x, +y on top and -y, +z, -z below, each oriented
+// as in OpenGL cube maps.
+pub struct Cubemap {
+    view: View,
+}
+
+impl Cubemap {
+    pub fn new(view: View) -> Self {
+        Cubemap { view }
+    }
+}
+
+impl Camera for Cubemap {
+    fn ray(&self, x: usize, y: usize, width: usize, height: usize) -> Option<Ray> {
+        let (u, v) = image_point(x, y, width, height);
+        let (column, row) = ((u * 3.0).min(2.0), (v * 2.0).min(1.0));
+        let face = column as usize + 3 * row as usize;
+        let a = (u * 3.0 - column.floor()) * 2.0 - 1.0;
+        let b = (v * 2.0 - row.floor()) * 2.0 - 1.0;
+
+        let local = match face {
+            0 => Vector3::new(1.0, -b, -a),
+            1 => Vector3::new(-1.0, -b, a),
+            2 => Vector3::new(a, 1.0, b),
+            3 => Vector3::new(a, -1.0, -b),
+            4 => Vector3::new(a, -b, 1.0),
+            _ => Vector3::new(-a, -b, -1.0),
+        };
+        Some(Ray {
+            origin: self.view.position,
+            direction: self.view.to_world(&local.normalize()),
+            time: 0.0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use nalgebra::Point3;
+
+    #[test]
+    fn panoramas_look_forward_from_their_centres() {
+        let view = View::look_at(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+        );
+        let forward = Vector3::new(1.0, 0.0, 0.0);
+
+        let ray = Equirectangular::new(view).ray(500, 250, 1001, 501).unwrap();
+        assert!((ray.direction - forward).norm() < 0.05);
+
+        // The middle of the -z face, bottom right of the grid.
+        let ray = Cubemap::new(view).ray(250, 75, 300, 100).unwrap();
+        assert!((ray.direction - forward).norm() < 0.05);
+    }
+}
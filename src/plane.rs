@@ -0,0 +1,117 @@
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::shape::{facing, Frame, Shape, Span, Surface, BIAS};
+use nalgebra::{Point2, Point3, Vector3};
+use std::f64;
+
+pub struct Plane {
+    frame: Frame,
+    normal: Vector3<f64>,
+    material: Material,
+}
+
+impl Plane {
+    pub fn new(point: Point3<f64>, normal: Vector3<f64>, material: Material) -> Self {
+        Plane {
+            frame: Frame::new(point, normal),
+            normal: normal.normalize(),
+            material,
+        }
+    }
+}
+
+impl Shape for Plane {
+    fn intersection_distance(&self, ray: &Ray) -> f64 {
+        let local = self.frame.ray_to_local(ray);
+        if local.direction.y.abs() < f64::EPSILON {
+            return f64::INFINITY;
+        }
+
+        let t = -local.origin.y / local.direction.y;
+        if t > BIAS {
+            t
+        } else {
+            f64::INFINITY
+        }
+    }
+
+    fn surface(&self, ray: &Ray, distance: f64) -> Surface {
+        let point = self.frame.point_to_local(&(ray.origin + ray.direction * distance));
+        let (normal, front_face) = facing(self.normal, ray);
+        Surface {
+            normal,
+            uv: Point2::new(point.x, point.z),
+            front_face,
+        }
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    // Everything behind the plane counts as inside, so planes can slice
+    // other solids in half.
+    fn spans(&self, ray: &Ray) -> Vec<Span> {
+        let local = self.frame.ray_to_local(ray);
+        if local.direction.y.abs() < f64::EPSILON {
+            return if local.origin.y < 0.0 {
+                vec![Span {
+                    enter: f64::NEG_INFINITY,
+                    exit: f64::INFINITY,
+                }]
+            } else {
+                vec![]
+            };
+        }
+
+        let t = -local.origin.y / local.direction.y;
+        if local.direction.y > 0.0 {
+            vec![Span {
+                enter: f64::NEG_INFINITY,
+                exit: t,
+            }]
+        } else {
+            vec![Span {
+                enter: t,
+                exit: f64::INFINITY,
+            }]
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn normals_face_the_ray_and_uvs_measure_distance_along_the_plane() {
+        let material = Material::new(
+            Vector3::new(1.0, 1.0, 1.0),
+            1.0,
+            0.0,
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.04, 0.04, 0.04),
+            0.0,
+            0.0,
+        );
+        let plane = Plane::new(Point3::new(0.0, 1.0, 0.0), Vector3::new(0.0, 2.0, 0.0), material);
+        let ray = |origin: Point3<f64>, direction: Vector3<f64>| Ray {
+            origin,
+            direction,
+            time: 0.0,
+        };
+
+        let above = ray(Point3::new(0.0, 3.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        assert!((plane.intersection_distance(&above) - 2.0).abs() < 1e-9);
+        let surface = plane.surface(&above, 2.0);
+        assert_eq!(surface.normal, Vector3::new(0.0, 1.0, 0.0));
+        assert!(surface.front_face);
+        assert!(surface.uv.coords.norm() < 1e-9);
+
+        let below = ray(Point3::new(3.0, -1.0, 4.0), Vector3::new(0.0, 1.0, 0.0));
+        let surface = plane.surface(&below, plane.intersection_distance(&below));
+        assert_eq!(surface.normal, Vector3::new(0.0, -1.0, 0.0));
+        assert!(!surface.front_face);
+        assert!((surface.uv.coords.norm() - 5.0).abs() < 1e-9);
+    }
+}
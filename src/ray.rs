@@ -3,4 +3,6 @@ use nalgebra::{Point3, Vector3};
 pub struct Ray {
     pub origin: Point3<f64>,
     pub direction: Vector3<f64>,
+    // When the ray was sent, somewhere in the interval the shutter is open.
+    pub time: f64,
 }
@@ -0,0 +1,142 @@
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::shape::{facing, Shape, Surface, SurfaceSample, BIAS};
+use nalgebra::{Point2, Point3, Vector3};
+use std::f64;
+
+pub struct Rectangle {
+    corner: Point3<f64>,
+    edge_u: Vector3<f64>,
+    edge_v: Vector3<f64>,
+    normal: Vector3<f64>,
+    material: Material,
+}
+
+impl Rectangle {
+    pub fn new(
+        corner: Point3<f64>,
+        edge_u: Vector3<f64>,
+        edge_v: Vector3<f64>,
+        material: Material,
+    ) -> Self {
+        Rectangle {
+            corner,
+            edge_u,
+            edge_v,
+            normal: edge_u.cross(&edge_v).normalize(),
+            material,
+        }
+    }
+
+    pub fn axis_aligned(min: Point3<f64>, max: Point3<f64>, material: Material) -> Self {
+        let size = max - min;
+        let (edge_u, edge_v) = if size.x == 0.0 {
+            (Vector3::new(0.0, 0.0, size.z), Vector3::new(0.0, size.y, 0.0))
+        } else if size.y == 0.0 {
+            (Vector3::new(size.x, 0.0, 0.0), Vector3::new(0.0, 0.0, size.z))
+        } else {
+            (Vector3::new(size.x, 0.0, 0.0), Vector3::new(0.0, size.y, 0.0))
+        };
+        Rectangle::new(min, edge_u, edge_v, material)
+    }
+
+    fn coordinates(&self, point: &Point3<f64>) -> Point2<f64> {
+        let offset = point - self.corner;
+        Point2::new(
+            offset.dot(&self.edge_u) / self.edge_u.norm_squared(),
+            offset.dot(&self.edge_v) / self.edge_v.norm_squared(),
+        )
+    }
+}
+
+impl Shape for Rectangle {
+    fn intersection_distance(&self, ray: &Ray) -> f64 {
+        let denominator = self.normal.dot(&ray.direction);
+        if denominator.abs() < f64::EPSILON {
+            return f64::INFINITY;
+        }
+
+        let t = (self.corner - ray.origin).dot(&self.normal) / denominator;
+        if t <= BIAS {
+            return f64::INFINITY;
+        }
+
+        let uv = self.coordinates(&(ray.origin + ray.direction * t));
+        if uv.x < 0.0 || uv.x > 1.0 || uv.y < 0.0 || uv.y > 1.0 {
+            f64::INFINITY
+        } else {
+            t
+        }
+    }
+
+    fn surface(&self, ray: &Ray, distance: f64) -> Surface {
+        let (normal, front_face) = facing(self.normal, ray);
+        Surface {
+            normal,
+            uv: self.coordinates(&(ray.origin + ray.direction * distance)),
+            front_face,
+        }
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn area(&self) -> f64 {
+        self.edge_u.cross(&self.edge_v).norm()
+    }
+
+    fn sample_surface(&self) -> Option<SurfaceSample> {
+        Some(SurfaceSample {
+            point: self.corner
+                + self.edge_u * rand::random::<f64>()
+                + self.edge_v * rand::random::<f64>(),
+            normal: self.normal,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn normals_face_the_ray_and_uvs_run_along_the_edges() {
+        let material = Material::new(
+            Vector3::new(1.0, 1.0, 1.0),
+            1.0,
+            0.0,
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.04, 0.04, 0.04),
+            0.0,
+            0.0,
+        );
+        let rectangle = Rectangle::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, 4.0),
+            Vector3::new(2.0, 0.0, 0.0),
+            material,
+        );
+        let ray = |origin: Point3<f64>, direction: Vector3<f64>| Ray {
+            origin,
+            direction,
+            time: 0.0,
+        };
+
+        let above = ray(Point3::new(1.0, 1.0, 1.0), Vector3::new(0.0, -1.0, 0.0));
+        assert!((rectangle.intersection_distance(&above) - 1.0).abs() < 1e-9);
+        let surface = rectangle.surface(&above, 1.0);
+        assert_eq!(surface.normal, Vector3::new(0.0, 1.0, 0.0));
+        assert!(surface.front_face);
+        assert!((surface.uv - Point2::new(0.25, 0.5)).norm() < 1e-9);
+
+        let below = ray(Point3::new(1.5, -1.0, 3.0), Vector3::new(0.0, 1.0, 0.0));
+        let surface = rectangle.surface(&below, rectangle.intersection_distance(&below));
+        assert_eq!(surface.normal, Vector3::new(0.0, -1.0, 0.0));
+        assert!(!surface.front_face);
+        assert!((surface.uv - Point2::new(0.75, 0.75)).norm() < 1e-9);
+
+        let beside = ray(Point3::new(2.5, 1.0, 1.0), Vector3::new(0.0, -1.0, 0.0));
+        assert_eq!(rectangle.intersection_distance(&beside), f64::INFINITY);
+    }
+}
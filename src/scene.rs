@@ -1,34 +1,168 @@
 use crate::camera::Camera;
 use crate::material::Material;
+use crate::medium::Medium;
+use crate::nodes::ShadingPoint;
 use crate::ray::Ray;
-use crate::sphere::Sphere;
-use nalgebra::{Point3, Vector3};
+use crate::shape::{Shape, BIAS};
+use nalgebra::{Point2, Point3, Vector3};
+use std::borrow::Cow;
+use std::cmp::Ordering;
 
 pub struct Intersection<'a> {
     pub hit: Point3<f64>,
     pub normal: Vector3<f64>,
-    pub material: &'a Material,
+    pub tangent: Vector3<f64>,
+    pub uv: Point2<f64>,
+    pub front_face: bool,
+    pub object: &'a dyn Shape,
+    // The object's material with any shader evaluated at the hit.
+    pub material: Cow<'a, Material>,
     pub distance: f64,
 }
 
+pub struct LightSample {
+    pub direction: Vector3<f64>,
+    pub radiance: Vector3<f64>,
+    pub pdf: f64,
+}
+
+// Most masked hits a ray skips through on one object, such as across the
+// leaves of a single card, before the rest are treated as solid.
+const MAX_MASKED_HITS: usize = 64;
+
+// Rays cast through a pixel when focusing on it.
+const AUTOFOCUS_RAYS: usize = 16;
+
 struct Hit<'a> {
-    object: &'a Sphere,
+    object: &'a dyn Shape,
     distance: f64,
 }
 
 pub struct Scene {
-    pub camera: Camera,
-    objects: Vec<Sphere>,
+    pub camera: Box<dyn Camera>,
+    pub medium: Option<Medium>,
+    objects: Vec<Box<dyn Shape>>,
+    lights: Vec<usize>,
+    spectral: bool,
 }
 
 impl Scene {
-    pub fn new(objects: Vec<Sphere>, camera: Camera) -> Scene {
-        Scene { objects, camera }
+    pub fn new<C: Camera + 'static>(objects: Vec<Box<dyn Shape>>, camera: C) -> Scene {
+        let lights = objects
+            .iter()
+            .enumerate()
+            .filter(|(_, object)| is_sampled_light(object.as_ref()))
+            .map(|(index, _)| index)
+            .collect();
+
+        Scene {
+            objects,
+            camera: Box::new(camera),
+            medium: None,
+            lights,
+            spectral: false,
+        }
+    }
+
+    // Fills all of the space between objects, for example with fog.
+    pub fn with_medium(mut self, medium: Medium) -> Self {
+        self.medium = Some(medium);
+        self
+    }
+
+    // Asks for the scene to be rendered spectrally, as scenes with
+    // dispersive glass need to show their rainbows.
+    pub fn with_spectral_rendering(mut self) -> Self {
+        self.spectral = true;
+        self
+    }
+
+    pub fn is_spectral(&self) -> bool {
+        self.spectral
+    }
+
+    // Picks an emitter uniformly and a point on it uniformly by area. The
+    // radiance returned is already divided by the probability of the sample.
+    pub fn sample_light(&self, point: &Point3<f64>, time: f64, medium: Option<Medium>) -> Option<LightSample> {
+        if self.lights.is_empty() {
+            return None;
+        }
+
+        let choice = (rand::random::<f64>() * self.lights.len() as f64) as usize;
+        let light = self.objects[self.lights[choice.min(self.lights.len() - 1)]].as_ref();
+        let sample = light.sample_surface()?;
+
+        let offset = sample.point + light.displacement(time) - point;
+        let distance = offset.norm();
+        let direction = offset / distance;
+        let cos_light = sample.normal.dot(&-direction);
+        let radiance = light.material().emit(cos_light > 0.0, light.area())?;
+
+        let transmittance = self.transmittance(point, &direction, distance, time, medium);
+        if transmittance.max() == 0.0 {
+            return None;
+        }
+
+        let pdf = self.light_pdf(light, distance, cos_light);
+        Some(LightSample {
+            direction,
+            radiance: radiance.component_mul(&transmittance) / pdf,
+            pdf,
+        })
+    }
+
+    // Solid angle density with which `sample_light` picks a point on `light`.
+    pub fn light_pdf(&self, light: &dyn Shape, distance: f64, cos_light: f64) -> f64 {
+        distance * distance / (cos_light.abs() * light.area() * self.lights.len() as f64)
+    }
+
+    // Fraction of light which makes it between two points. Only null
+    // surfaces, such as the boundaries of volumes, can be passed through.
+    fn transmittance(
+        &self,
+        point: &Point3<f64>,
+        direction: &Vector3<f64>,
+        distance: f64,
+        time: f64,
+        mut medium: Option<Medium>,
+    ) -> Vector3<f64> {
+        let mut transmittance = Vector3::new(1.0, 1.0, 1.0);
+        let mut ray = Ray {
+            origin: *point,
+            direction: *direction,
+            time,
+        };
+        let mut remaining = distance * (1.0 - 1e-6);
+
+        loop {
+            let intersect = match self.intersect(&ray) {
+                Some(intersect) if intersect.distance < remaining => intersect,
+                _ => break,
+            };
+            if !intersect.material.is_null() {
+                return Vector3::zeros();
+            }
+
+            if let Some(ref medium) = medium {
+                transmittance.component_mul_assign(&medium.transmittance(&ray, intersect.distance));
+            }
+            if let Some(interior) = intersect.material.interior() {
+                medium = if intersect.front_face { Some(interior) } else { self.medium.clone() };
+            }
+            remaining -= intersect.distance;
+            ray.origin = intersect.hit;
+        }
+
+        if let Some(ref medium) = medium {
+            transmittance.component_mul_assign(&medium.transmittance(&ray, remaining));
+        }
+        transmittance
     }
 
-    pub fn intersect(&self, ray: &Ray) -> Option<Intersection> {
+    pub fn intersect(&self, ray: &Ray) -> Option<Intersection<'_>> {
         self.objects.iter().fold(None, |closest, object| {
-            let distance = object.intersection_distance(ray);
+            let object = object.as_ref();
+            let distance = unmasked_distance(object, ray);
             match closest {
                 None => Some(Hit{object, distance}),
                 Some(ref hit) if distance < hit.distance => Some(Hit{object, distance}),
@@ -36,28 +170,85 @@ impl Scene {
             }
         }).map(|hit| {
             let point = ray.origin + (ray.direction * hit.distance);
-            let normal = (point - hit.object.center()).normalize();
+            let surface = hit.object.surface(ray, hit.distance);
+            let material = hit.object.material().shade(&ShadingPoint {
+                position: point,
+                normal: surface.normal,
+                view: -ray.direction,
+                uv: surface.uv,
+            });
             Intersection {
                 hit: point,
-                normal,
-                material: hit.object.material(),
+                normal: surface.normal,
+                tangent: hit.object.tangent(&(point - hit.object.displacement(ray.time)), &surface.normal),
+                uv: surface.uv,
+                front_face: surface.front_face,
+                object: hit.object,
+                material,
                 distance: hit.distance,
             }
         })
     }
 
-    pub fn bg(&self, ray: &Ray) -> Vector3<f64> {
+    // Focuses the camera on whatever is seen through the pixel at (x, y),
+    // giving the distance it focused at. Several rays are cast through the
+    // pixel and the middle of their hits is used, so that a wide aperture
+    // doesn't land focus on something beside what was picked.
+    pub fn autofocus(&mut self, x: usize, y: usize, width: usize, height: usize) -> Option<f64> {
+        let mut hits: Vec<(f64, Point3<f64>)> = (0..AUTOFOCUS_RAYS)
+            .filter_map(|_| self.camera.ray(x, y, width, height))
+            .filter_map(|ray| self.intersect(&ray).map(|intersect| (intersect.distance, intersect.hit)))
+            .filter(|(distance, _)| distance.is_finite())
+            .collect();
+        hits.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+
+        let (_, point) = *hits.get(hits.len() / 2)?;
+        self.camera.focus_on(&point)
+    }
+
+    pub fn bg(&self, _ray: &Ray) -> Vector3<f64> {
         Vector3::new(1.0, 0.0, 0.0)
     }
 }
 
+// Distance to the first hit on the object which isn't cut away by its
+// material's opacity.
+fn unmasked_distance(object: &dyn Shape, ray: &Ray) -> f64 {
+    let material = object.material();
+    let mut distance = object.intersection_distance(ray);
+    if !material.has_opacity() {
+        return distance;
+    }
+
+    for _ in 0..MAX_MASKED_HITS {
+        if distance.is_infinite() || material.is_opaque_at(&object.surface(ray, distance).uv) {
+            return distance;
+        }
+        let beyond = Ray {
+            origin: ray.origin + ray.direction * (distance + BIAS),
+            direction: ray.direction,
+            time: ray.time,
+        };
+        distance += BIAS + object.intersection_distance(&beyond);
+    }
+    distance
+}
+
+// Emitters are sampled directly when they have a finite area to sample.
+pub fn is_sampled_light(object: &dyn Shape) -> bool {
+    let area = object.area();
+    object.material().is_emissive() && area > 0.0 && area.is_finite()
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::camera::Camera;
-    use crate::material::Material;
+    use crate::camera::ThinLens;
+    use crate::disk::Disk;
+    use crate::material::{Emission, Material};
     use crate::ray::Ray;
     use crate::sphere::Sphere;
+    use crate::texture::Texture;
     use nalgebra::{Point3, Vector3};
 
     #[test]
@@ -72,18 +263,18 @@ mod test {
             0.2,
         );
 
-        let objects = vec![
-            Sphere::new(Point3::new(-1005.0, 0.0, -8.0), 1000.0, blue_plastic),
-            Sphere::new(Point3::new(1005.0, 0.0, -8.0), 1000.0, blue_plastic),
-            Sphere::new(Point3::new(0.0, -1003.0, -8.0), 1000.0, blue_plastic),
-            Sphere::new(Point3::new(0.0, 1003.0, -8.0), 1000.0, blue_plastic),
-            Sphere::new(Point3::new(0.0, 0.0, -1010.0), 1000.0, blue_plastic),
-            Sphere::new(Point3::new(0.0, 13.0, -8.0), 10.5, blue_plastic),
-            Sphere::new(Point3::new(1.0, -2.0, -7.0), 1.0, blue_plastic),
-            Sphere::new(Point3::new(-0.75, -2.0, -5.0), 1.0, blue_plastic),
+        let objects: Vec<Box<dyn Shape>> = vec![
+            Box::new(Sphere::new(Point3::new(-1005.0, 0.0, -8.0), 1000.0, blue_plastic.clone())),
+            Box::new(Sphere::new(Point3::new(1005.0, 0.0, -8.0), 1000.0, blue_plastic.clone())),
+            Box::new(Sphere::new(Point3::new(0.0, -1003.0, -8.0), 1000.0, blue_plastic.clone())),
+            Box::new(Sphere::new(Point3::new(0.0, 1003.0, -8.0), 1000.0, blue_plastic.clone())),
+            Box::new(Sphere::new(Point3::new(0.0, 0.0, -1010.0), 1000.0, blue_plastic.clone())),
+            Box::new(Sphere::new(Point3::new(0.0, 13.0, -8.0), 10.5, blue_plastic.clone())),
+            Box::new(Sphere::new(Point3::new(1.0, -2.0, -7.0), 1.0, blue_plastic.clone())),
+            Box::new(Sphere::new(Point3::new(-0.75, -2.0, -5.0), 1.0, blue_plastic)),
         ];
 
-        let camera = Camera::new(
+        let camera = ThinLens::new(
             Point3::new(0.0, 0.0, 7.0),
             0.024,
             0.040,
@@ -97,6 +288,7 @@ mod test {
         let ray = Ray {
             origin: Point3::new(0.0, 0.0, 7.0),
             direction: Vector3::new(-0.13133105101029943, 0.23858981742286559, -0.96219907195063),
+            time: 0.0,
         };
 
         let intersection = scene.intersect(&ray).unwrap();
@@ -109,4 +301,125 @@ mod test {
             )
         );
     }
+
+    #[test]
+    fn cut_away_surfaces_are_skipped() {
+        let leaf = Material::new(
+            Vector3::new(0.2, 0.6, 0.1),
+            1.0,
+            0.0,
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, 0.0),
+            0.0,
+            0.0,
+        );
+        let hole = leaf.clone().with_cutout(Texture::constant(0.2), 0.5);
+        let solid = leaf.with_cutout(Texture::constant(0.8), 0.5);
+
+        let objects: Vec<Box<dyn Shape>> = vec![
+            Box::new(Sphere::new(Point3::new(0.0, 0.0, -5.0), 1.0, hole)),
+            Box::new(Sphere::new(Point3::new(0.0, 0.0, -10.0), 1.0, solid)),
+        ];
+        let camera = ThinLens::new(Point3::new(0.0, 0.0, 0.0), 0.024, 0.040, 15.0, 1.4, 0.0, 0.0);
+        let scene = Scene::new(objects, camera);
+        let ray = Ray {
+            origin: Point3::new(0.0, 0.0, 0.0),
+            direction: Vector3::new(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+
+        let intersection = scene.intersect(&ray).unwrap();
+        assert!((intersection.distance - 9.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn autofocus_focuses_on_what_the_pixel_sees() {
+        let grey = Material::new(
+            Vector3::new(0.5, 0.5, 0.5),
+            1.0,
+            0.0,
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, 0.0),
+            0.0,
+            0.0,
+        );
+        let objects: Vec<Box<dyn Shape>> = vec![Box::new(Sphere::new(Point3::new(0.0, 0.0, -5.0), 1.0, grey))];
+        let camera = ThinLens::new(Point3::new(0.0, 0.0, 0.0), 0.024, 0.040, 15.0, 1.4, 0.0, 0.0);
+        let mut scene = Scene::new(objects, camera);
+
+        let distance = scene.autofocus(50, 50, 101, 101).unwrap();
+        assert!((distance - 4.0).abs() < 0.01);
+        assert!(scene.autofocus(0, 0, 101, 101).is_none());
+    }
+
+    fn lamp(emission: Emission, two_sided: bool) -> Scene {
+        let material = Material::new(
+            Vector3::new(0.0, 0.0, 0.0),
+            1.0,
+            0.0,
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, 0.0),
+            0.0,
+            0.0,
+        )
+        .emitting(emission, two_sided);
+        let disk = Disk::new(Point3::new(0.0, 2.0, 0.0), Vector3::new(0.0, -1.0, 0.0), 1.0, material);
+        let camera = ThinLens::new(Point3::new(0.0, 0.0, 0.0), 0.024, 0.040, 15.0, 1.4, 0.0, 0.0);
+        Scene::new(vec![Box::new(disk)], camera)
+    }
+
+    #[test]
+    fn power_is_spread_over_the_area_and_sides_of_a_light() {
+        let power = Vector3::new(2.0, 4.0, 6.0) * std::f64::consts::PI;
+        let one_sided = lamp(Emission::Power(power), false);
+        let two_sided = lamp(Emission::Power(power), true);
+        let emit = |scene: &Scene, front_face: bool| scene.objects[0].material().emit(front_face, 2.0);
+
+        assert_eq!(emit(&one_sided, true), Some(Vector3::new(1.0, 2.0, 3.0)));
+        assert_eq!(emit(&one_sided, false), None);
+        assert_eq!(emit(&two_sided, true), Some(Vector3::new(0.5, 1.0, 1.5)));
+        assert_eq!(emit(&two_sided, false), Some(Vector3::new(0.5, 1.0, 1.5)));
+
+        let radiance = lamp(Emission::Radiance(Vector3::new(1.0, 1.0, 1.0)), true);
+        assert_eq!(emit(&radiance, false), Some(Vector3::new(1.0, 1.0, 1.0)));
+    }
+
+    #[test]
+    fn sampled_lights_agree_with_the_light_pdf() {
+        let radiance = Vector3::new(1.0, 1.0, 1.0);
+        let scene = lamp(Emission::Radiance(radiance), false);
+        let light = scene.objects[0].as_ref();
+        let point = Point3::origin();
+
+        let samples = 20000;
+        let mut solid_angle = 0.0;
+        for _ in 0..samples {
+            let sample = scene.sample_light(&point, 0.0, None).unwrap();
+            let ray = Ray {
+                origin: point,
+                direction: sample.direction,
+                time: 0.0,
+            };
+            let distance = light.intersection_distance(&ray);
+            let cos_light = light.surface(&ray, distance).normal.dot(&-sample.direction);
+            let pdf = scene.light_pdf(light, distance, cos_light);
+
+            assert!((pdf - sample.pdf).abs() < 1e-6 * pdf);
+            assert!((sample.radiance * sample.pdf - radiance).norm() < 1e-9);
+            solid_angle += 1.0 / sample.pdf / samples as f64;
+        }
+
+        // The disk seen from below its centre.
+        let expected = 2.0 * std::f64::consts::PI * (1.0 - 2.0 / 5f64.sqrt());
+        assert!((solid_angle - expected).abs() < 0.02 * expected);
+    }
+
+    #[test]
+    fn only_two_sided_lights_shine_from_behind() {
+        let above = Point3::new(0.0, 3.0, 0.0);
+        let one_sided = lamp(Emission::Radiance(Vector3::new(1.0, 1.0, 1.0)), false);
+        let two_sided = lamp(Emission::Radiance(Vector3::new(1.0, 1.0, 1.0)), true);
+        assert!(one_sided.sample_light(&above, 0.0, None).is_none());
+        assert!(two_sided.sample_light(&above, 0.0, None).is_some());
+    }
 }
@@ -0,0 +1,852 @@
+use crate::animation::{Interpolation, Track};
+use crate::aperture::{Aperture, BokehImage};
+use crate::camera::{Camera, FieldOfView, ThinLens, View};
+use crate::conductor::Conductor;
+use crate::cuboid::Cuboid;
+use crate::disk::Disk;
+use crate::fisheye::{Fisheye, Mapping};
+use crate::lens::{parse_lens_table, LensElement, RealisticLens};
+use crate::material::{Emission, Input, Material};
+use crate::measured::MeasuredBrdf;
+use crate::medium::{Density, Medium};
+use crate::motion::{Moving, Shutter};
+use crate::nodes::{Node, NodeGraph};
+use crate::orthographic::Orthographic;
+use crate::panorama::{Cubemap, Equirectangular};
+use crate::plane::Plane;
+use crate::rectangle::Rectangle;
+use crate::scene::Scene;
+use crate::shape::Shape;
+use crate::spectrum::Dispersion;
+use crate::sphere::Sphere;
+use crate::stereo::{Layout, Stereo};
+use crate::texture::Texture;
+use crate::volume::{Noise, VoxelGrid};
+use nalgebra::{Point3, Vector3};
+use std::collections::HashMap;
+use std::fs;
+use std::rc::Rc;
+
+// Reads a scene from a text description with one statement per line. Words
+// are separated by whitespace and anything after a # is ignored.
+//
+//   node <name> <kind> <inputs...>
+//   material <name> <property> <values...> ...
+//   camera <x y z> <sensor> <focal length> <focus> <f-stop> <horizontal> <vertical>
+//   camera [<kind>] eye <x y z> target <x y z> <option> <values...> ...
+//   sphere <centre> <radius> <material>
+//   plane <point> <normal> <material>
+//   cuboid <min> <max> <material>
+//   rectangle <min> <max> <material>
+//   quad <corner> <edge u> <edge v> <material>
+//   disk <centre> <normal> <radius> <material>
+//   key <track> <time> <step|linear|bezier> <values...>
+//   spectral
+//
+// Cameras given an eye and target are thin_lens, realistic, orthographic,
+// fisheye, equirectangular or cubemap, and all take the options up and roll.
+// Thin lens cameras also take fov, hfov, sensor, focal_length, fstop, focus,
+// blades <count> <rotation>, bokeh <width> <height> <values...>, bokeh_image
+// <path> to a greyscale PGM image, cat_eye, shift <right> <up> in image
+// heights, and tilt and swing in degrees.
+// Realistic cameras take sensor and focus, along with either lens dgauss or
+// a row of a lens table in millimetres for each element <radius> <thickness>
+// <index> <aperture>. Orthographic cameras take height, and fisheyes take
+// fov and mapping. Any of them can be doubled up into a stereo pair with
+// stereo side_by_side or stereo over_under, spaced by interocular and turned
+// in to meet at the convergence distance. Stereo equirectangular cameras are rendered as
+// omnidirectional stereo. Any camera can also take shutter <open> <close>
+// for motion blur, and end_eye and end_target to move while it is open.
+//
+// Any shape can be followed by velocity <x y z> to move it over time.
+//
+// Node inputs and the colour, transparency, metal, gloss and roughness of a
+// material may be given either as numbers or as the name of a node.
+//
+// A material given measured <path> reflects as the MERL .binary file there
+// does, in place of its other surface properties.
+//
+// A material given absorption or scattering fills whatever it is put on with
+// a medium, whose phase is its anisotropy. Its density may also vary with
+// density noise <frequency> <octaves> <threshold> or density grid <path>,
+// reading a voxel grid file.
+//
+// Scenes with spectral in them are rendered spectrally, which dispersive
+// materials need to split light into its colours.
+//
+// Keys build up named tracks of one or more numbers, and $<track> anywhere
+// else stands for the track's values at the time the scene is read.
+pub fn parse_scene(text: &str) -> Result<Scene, String> {
+    parse_scene_at(text, 0.0)
+}
+
+// Reads the scene as it stands at `time`, in seconds.
+pub fn parse_scene_at(text: &str, time: f64) -> Result<Scene, String> {
+    parse_scene_with(text, time, &|path| fs::read(path).map_err(|error| format!("{}: {}", path, error)))
+}
+
+// Reads the scene, fetching any files it refers to with `read`.
+pub fn parse_scene_with(text: &str, time: f64, read: &dyn Fn(&str) -> Result<Vec<u8>, String>) -> Result<Scene, String> {
+    let lines: Vec<(usize, Vec<&str>)> = text
+        .lines()
+        .enumerate()
+        .map(|(number, line)| {
+            let code = line.split('#').next().unwrap_or("");
+            (number + 1, code.split_whitespace().collect::<Vec<_>>())
+        })
+        .filter(|(_, words)| !words.is_empty())
+        .collect();
+
+    let mut tracks = HashMap::new();
+    for (number, words) in lines.iter().filter(|(_, words)| words[0] == "key") {
+        let mut tokens = Tokens::new(*number, &words[1..]);
+        parse_key(&mut tokens, &mut tracks)?;
+    }
+    let substituted = lines
+        .iter()
+        .filter(|(_, words)| words[0] != "key")
+        .map(|(number, words)| Ok((*number, substitute(*number, words, &tracks, time)?)))
+        .collect::<Result<Vec<(usize, Vec<String>)>, String>>()?;
+    let statements: Vec<(usize, Vec<&str>)> = substituted
+        .iter()
+        .map(|(number, words)| (*number, words.iter().map(String::as_str).collect()))
+        .collect();
+
+    // Nodes are read first so that every material can share the finished
+    // graph.
+    let mut graph = NodeGraph::new();
+    let mut nodes = HashMap::new();
+    for (number, words) in statements.iter().filter(|(_, words)| words[0] == "node") {
+        let mut tokens = Tokens::new(*number, &words[1..]);
+        let name = tokens.word()?;
+        let node = parse_node(&mut tokens, &mut graph, &nodes)?;
+        tokens.finish()?;
+        nodes.insert(name.to_string(), node);
+    }
+    let graph = Rc::new(graph);
+
+    let mut materials = HashMap::new();
+    for (number, words) in statements.iter().filter(|(_, words)| words[0] == "material") {
+        let mut tokens = Tokens::new(*number, &words[1..]);
+        let name = tokens.word()?;
+        let material = parse_material(&mut tokens, &graph, &nodes, read)?;
+        materials.insert(name.to_string(), material);
+    }
+
+    let mut camera: Box<dyn Camera> =
+        Box::new(ThinLens::new(Point3::new(0.0, 0.0, 7.0), 0.024, 0.040, 15.0, 1.4, 0.0, 0.0));
+    let mut objects: Vec<Box<dyn Shape>> = vec![];
+    let mut spectral = false;
+    for (number, words) in &statements {
+        let mut tokens = Tokens::new(*number, &words[1..]);
+        match words[0] {
+            "node" | "material" => continue,
+            "camera" => camera = parse_camera(&mut tokens, read)?,
+            "spectral" => spectral = true,
+            shape => {
+                let object = parse_shape(shape, &mut tokens, &materials)?;
+                objects.push(match tokens.words.first() {
+                    Some(&"velocity") => {
+                        tokens.word()?;
+                        Box::new(Moving::new(object, tokens.vector()?))
+                    }
+                    _ => object,
+                });
+            }
+        }
+        tokens.finish()?;
+    }
+
+    let scene = Scene::new(objects, camera);
+    Ok(if spectral { scene.with_spectral_rendering() } else { scene })
+}
+
+// Adds a key to the named track, with one track kept for each of its
+// values.
+fn parse_key(tokens: &mut Tokens, tracks: &mut HashMap<String, Vec<Track<f64>>>) -> Result<(), String> {
+    let name = tokens.word()?;
+    let time = tokens.number()?;
+    let interpolation = match tokens.word()? {
+        "step" => Interpolation::Step,
+        "linear" => Interpolation::Linear,
+        "bezier" => Interpolation::Bezier,
+        other => return Err(tokens.error(&format!("unknown interpolation '{}'", other))),
+    };
+    let mut values = vec![];
+    while !tokens.is_empty() {
+        values.push(tokens.number()?);
+    }
+
+    let track = tracks
+        .entry(name.to_string())
+        .or_insert_with(|| values.iter().map(|_| Track::new()).collect());
+    if values.is_empty() || track.len() != values.len() {
+        return Err(tokens.error(&format!("keys of track '{}' need the same number of values", name)));
+    }
+    for (component, value) in track.iter_mut().zip(values) {
+        component.add_key(time, value, interpolation);
+    }
+    Ok(())
+}
+
+// Replaces each $<track> with the track's values at `time`.
+fn substitute(
+    number: usize,
+    words: &[&str],
+    tracks: &HashMap<String, Vec<Track<f64>>>,
+    time: f64,
+) -> Result<Vec<String>, String> {
+    let mut substituted = vec![];
+    for word in words {
+        if let Some(name) = word.strip_prefix('$') {
+            let track = tracks
+                .get(name)
+                .ok_or_else(|| format!("line {}: unknown track '{}'", number, name))?;
+            substituted.extend(track.iter().filter_map(|component| component.value_at(time)).map(|value| value.to_string()));
+        } else {
+            substituted.push(word.to_string());
+        }
+    }
+    Ok(substituted)
+}
+
+fn parse_node(tokens: &mut Tokens, graph: &mut NodeGraph, nodes: &HashMap<String, usize>) -> Result<usize, String> {
+    let kind = tokens.word()?;
+    let node = match kind {
+        "constant" => Node::Constant(tokens.colour()?),
+        "checker" => Node::Texture(Texture::checker(tokens.number()?, tokens.number()?, tokens.number()?)),
+        "gradient" => Node::Texture(Texture::gradient(tokens.number()?, tokens.number()?)),
+        "noise" => Node::Texture(Texture::noise(
+            tokens.number()?,
+            tokens.number()? as usize,
+            tokens.number()?,
+            tokens.number()?,
+        )),
+        "mix" => Node::Mix(
+            tokens.input(graph, nodes)?,
+            tokens.input(graph, nodes)?,
+            tokens.input(graph, nodes)?,
+        ),
+        "multiply" => Node::Multiply(tokens.input(graph, nodes)?, tokens.input(graph, nodes)?),
+        "add" => Node::Add(tokens.input(graph, nodes)?, tokens.input(graph, nodes)?),
+        "remap" => Node::Remap(
+            tokens.input(graph, nodes)?,
+            tokens.number()?,
+            tokens.number()?,
+            tokens.number()?,
+            tokens.number()?,
+        ),
+        "fresnel" => Node::Fresnel(tokens.number()?),
+        "layer_weight" => Node::LayerWeight(tokens.number()?),
+        "ramp" => {
+            let input = tokens.input(graph, nodes)?;
+            let mut stops = vec![];
+            while !tokens.is_empty() {
+                stops.push((tokens.number()?, tokens.vector()?));
+            }
+            stops.sort_by(|a: &(f64, Vector3<f64>), b| a.0.total_cmp(&b.0));
+            Node::Ramp(input, stops)
+        }
+        _ => return Err(tokens.error(&format!("unknown node kind '{}'", kind))),
+    };
+    graph
+        .add(node)
+        .ok_or_else(|| tokens.error("node takes an input which doesn't exist"))
+}
+
+// An input which is either a fixed value or driven by a node.
+enum Value {
+    Fixed(Vector3<f64>),
+    Node(usize),
+}
+
+fn parse_material(
+    tokens: &mut Tokens,
+    graph: &Rc<NodeGraph>,
+    nodes: &HashMap<String, usize>,
+    read: &dyn Fn(&str) -> Result<Vec<u8>, String>,
+) -> Result<Material, String> {
+    let mut inputs = vec![];
+    let mut refraction = 1.0;
+    let mut reflectance = Vector3::new(0.04, 0.04, 0.04);
+    let mut emission = None;
+    let mut two_sided = false;
+    let mut absorption = None;
+    let mut scattering = None;
+    let mut phase = 0.0;
+    let mut density = Density::Uniform;
+    let mut builders: Vec<Box<dyn Fn(Material) -> Material>> = vec![];
+
+    while !tokens.is_empty() {
+        let property = tokens.word()?;
+        match property {
+            "color" => inputs.push((Input::Color, tokens.value(nodes)?)),
+            "transparency" => inputs.push((Input::Transparency, tokens.value(nodes)?)),
+            "metal" => inputs.push((Input::Metal, tokens.value(nodes)?)),
+            "gloss" => inputs.push((Input::Gloss, tokens.value(nodes)?)),
+            "roughness" => inputs.push((Input::Roughness, tokens.value(nodes)?)),
+            "refraction" => refraction = tokens.number()?,
+            "reflectance" => reflectance = tokens.colour()?,
+            "light" => emission = Some(Emission::Radiance(tokens.colour()?)),
+            "power" => emission = Some(Emission::Power(tokens.colour()?)),
+            "two_sided" => two_sided = true,
+            "conductor" => {
+                let name = tokens.word()?;
+                let conductor = Conductor::named(name)
+                    .ok_or_else(|| tokens.error(&format!("unknown conductor '{}'", name)))?;
+                builders.push(Box::new(move |material| material.with_conductor(conductor)));
+            }
+            "dispersion" => {
+                let dispersion = match tokens.word()? {
+                    "bk7" => Dispersion::bk7(),
+                    "sf11" => Dispersion::sf11(),
+                    "cauchy" => Dispersion::cauchy(tokens.number()?, tokens.number()?),
+                    glass => return Err(tokens.error(&format!("unknown dispersion '{}'", glass))),
+                };
+                builders.push(Box::new(move |material| material.with_dispersion(dispersion)));
+            }
+            "clearcoat" => {
+                let (index, gloss) = (tokens.number()?, tokens.number()?);
+                builders.push(Box::new(move |material| material.with_clearcoat(index, gloss)));
+            }
+            "sheen" => {
+                let colour = tokens.colour()?;
+                builders.push(Box::new(move |material| material.with_sheen(colour)));
+            }
+            "anisotropy" => {
+                let (alpha_x, alpha_y) = (tokens.number()?, tokens.number()?);
+                builders.push(Box::new(move |material| material.with_anisotropy(alpha_x, alpha_y)));
+            }
+            "measured" => {
+                let path = tokens.word()?;
+                let brdf = MeasuredBrdf::from_bytes(&read(path).map_err(|error| tokens.error(&error))?)
+                    .ok_or_else(|| tokens.error(&format!("'{}' isn't a MERL BRDF", path)))?;
+                let brdf = Rc::new(brdf);
+                builders.push(Box::new(move |material| material.with_measured(brdf.clone())));
+            }
+            "absorption" => absorption = Some(tokens.colour()?),
+            "scattering" => scattering = Some(tokens.colour()?),
+            "phase" => phase = tokens.number()?,
+            "density" => {
+                density = match tokens.word()? {
+                    "noise" => Density::Noise(Noise::new(
+                        tokens.number()?,
+                        tokens.number()? as usize,
+                        tokens.number()?,
+                    )),
+                    "grid" => {
+                        let path = tokens.word()?;
+                        let grid = VoxelGrid::from_bytes(&read(path).map_err(|error| tokens.error(&error))?)
+                            .ok_or_else(|| tokens.error(&format!("'{}' isn't a voxel grid", path)))?;
+                        Density::Grid(Rc::new(grid))
+                    }
+                    other => return Err(tokens.error(&format!("unknown density '{}'", other))),
+                }
+            }
+            _ => return Err(tokens.error(&format!("unknown material property '{}'", property))),
+        }
+    }
+
+    let fixed = |input: Input, default: Vector3<f64>| {
+        inputs
+            .iter()
+            .rev()
+            .find_map(|(bound, value)| match (bound, value) {
+                (bound, Value::Fixed(fixed)) if *bound == input => Some(*fixed),
+                _ => None,
+            })
+            .unwrap_or(default)
+    };
+    let grey = |value: f64| Vector3::new(value, value, value);
+
+    let mut material = Material::new(
+        fixed(Input::Color, grey(0.8)),
+        refraction,
+        fixed(Input::Transparency, grey(0.0)).x,
+        Vector3::zeros(),
+        reflectance,
+        fixed(Input::Metal, grey(0.0)).x,
+        fixed(Input::Gloss, grey(0.0)).x,
+    );
+    if let Some(emission) = emission {
+        material = material.emitting(emission, two_sided);
+    }
+    if absorption.is_some() || scattering.is_some() {
+        let medium = Medium::new(
+            absorption.unwrap_or_else(Vector3::zeros),
+            scattering.unwrap_or_else(Vector3::zeros),
+            phase,
+        );
+        material = material.with_interior(medium.with_density(density));
+    }
+    for builder in &builders {
+        material = builder(material);
+    }
+    material = material.with_diffuse_roughness(fixed(Input::Roughness, grey(0.0)).x);
+    for (input, value) in &inputs {
+        if let Value::Node(node) = value {
+            material = material.with_input(*input, graph.clone(), *node);
+        }
+    }
+    Ok(material)
+}
+
+fn parse_camera(tokens: &mut Tokens, read: &dyn Fn(&str) -> Result<Vec<u8>, String>) -> Result<Box<dyn Camera>, String> {
+    if tokens.peek_number().is_some() {
+        return Ok(Box::new(ThinLens::new(
+            tokens.point()?,
+            tokens.number()?,
+            tokens.number()?,
+            tokens.number()?,
+            tokens.number()?,
+            tokens.number()?,
+            tokens.number()?,
+        )));
+    }
+
+    let kind = match tokens.words.first() {
+        Some(&kind) if kind != "eye" && kind != "target" => tokens.word()?,
+        _ => "thin_lens",
+    };
+
+    let mut options = CameraOptions::default();
+    let mut eye = None;
+    let mut target = None;
+    let mut up = Vector3::new(0.0, 1.0, 0.0);
+    let mut roll = 0.0;
+    let mut stereo = None;
+    let mut interocular = 0.064;
+    let mut convergence = None;
+    let mut shutter = None;
+    let mut end_eye = None;
+    let mut end_target = None;
+
+    while !tokens.is_empty() {
+        let option = tokens.word()?;
+        match option {
+            "eye" => eye = Some(tokens.point()?),
+            "target" => target = Some(tokens.point()?),
+            "up" => up = tokens.vector()?,
+            "roll" => roll = tokens.number()?,
+            "fov" => options.field_of_view = Some(FieldOfView::Vertical(tokens.number()?)),
+            "hfov" => options.field_of_view = Some(FieldOfView::Horizontal(tokens.number()?)),
+            "sensor" => options.sensor = tokens.number()?,
+            "focal_length" => options.focal_length = tokens.number()?,
+            "fstop" => options.fstop = tokens.number()?,
+            "focus" => options.focus = Some(tokens.number()?),
+            "height" => options.height = tokens.number()?,
+            "blades" => {
+                let (blades, rotation) = (tokens.number()?, tokens.number()?);
+                options.aperture = Aperture::polygon(blades as usize, rotation);
+            }
+            "bokeh" => {
+                let (width, height) = (tokens.number()? as usize, tokens.number()? as usize);
+                let count = width
+                    .checked_mul(height)
+                    .ok_or_else(|| tokens.error("bokeh image is too large"))?;
+                let values = (0..count)
+                    .map(|_| tokens.number())
+                    .collect::<Result<Vec<f64>, String>>()?;
+                let image = BokehImage::new(width, height, &values)
+                    .ok_or_else(|| tokens.error("bokeh image needs some open pixels"))?;
+                options.aperture = Aperture::Image(Rc::new(image));
+            }
+            "bokeh_image" => {
+                let path = tokens.word()?;
+                let image = BokehImage::from_pgm(&read(path).map_err(|error| tokens.error(&error))?)
+                    .ok_or_else(|| tokens.error(&format!("'{}' isn't a greyscale PGM image with open pixels", path)))?;
+                options.aperture = Aperture::Image(Rc::new(image));
+            }
+            "cat_eye" => options.cat_eye = tokens.number()?,
+            "shift" => options.shift = (tokens.number()?, tokens.number()?),
+            "tilt" => options.tilt = tokens.number()?,
+            "swing" => options.swing = tokens.number()?,
+            "lens" => {
+                options.lens = match tokens.word()? {
+                    "dgauss" => parse_lens_table(include_str!("../lenses/dgauss.50mm.dat"))
+                        .expect("the built in lens table should be well formed"),
+                    other => return Err(tokens.error(&format!("unknown lens '{}'", other))),
+                }
+            }
+            "element" => options.lens.push(LensElement {
+                radius: tokens.number()? * 1e-3,
+                thickness: tokens.number()? * 1e-3,
+                index: tokens.number()?,
+                aperture: tokens.number()? * 1e-3,
+            }),
+            "mapping" => {
+                options.mapping = match tokens.word()? {
+                    "equidistant" => Mapping::Equidistant,
+                    "equisolid" => Mapping::Equisolid,
+                    other => return Err(tokens.error(&format!("unknown fisheye mapping '{}'", other))),
+                }
+            }
+            "stereo" => {
+                stereo = Some(match tokens.word()? {
+                    "side_by_side" => Layout::SideBySide,
+                    "over_under" => Layout::OverUnder,
+                    other => return Err(tokens.error(&format!("unknown stereo layout '{}'", other))),
+                })
+            }
+            "interocular" => interocular = tokens.number()?,
+            "convergence" => convergence = Some(tokens.number()?),
+            "shutter" => shutter = Some((tokens.number()?, tokens.number()?)),
+            "end_eye" => end_eye = Some(tokens.point()?),
+            "end_target" => end_target = Some(tokens.point()?),
+            _ => return Err(tokens.error(&format!("unknown camera option '{}'", option))),
+        }
+    }
+
+    let eye = eye.ok_or_else(|| tokens.error("camera needs an eye"))?;
+    let target = target.ok_or_else(|| tokens.error("camera needs a target"))?;
+    if eye == target || end_eye.unwrap_or(eye) == end_target.unwrap_or(target) {
+        return Err(tokens.error("camera eye and target are the same point"));
+    }
+    let view = View::look_at(eye, target, up).with_roll(roll);
+    options.focus = options.focus.or_else(|| Some((target - eye).norm()));
+
+    let build = |view: View| options.build(kind, view).map_err(|error| tokens.error(&error));
+    let camera = match stereo {
+        None => build(view)?,
+        Some(layout) if kind == "equirectangular" => Box::new(Stereo::omnidirectional(view, interocular, layout)),
+        Some(layout) => {
+            let convergence = convergence.unwrap_or_else(|| (target - eye).norm());
+            let left = build(view.eye(-0.5 * interocular, convergence))?;
+            let right = build(view.eye(0.5 * interocular, convergence))?;
+            Box::new(Stereo::new(left, right, layout))
+        }
+    };
+
+    if shutter.is_none() && end_eye.is_none() && end_target.is_none() {
+        return Ok(camera);
+    }
+    let (open, close) = shutter.unwrap_or((0.0, 1.0));
+    let mut camera = Shutter::new(camera, open, close);
+    if end_eye.is_some() || end_target.is_some() {
+        let end = View::look_at(end_eye.unwrap_or(eye), end_target.unwrap_or(target), up).with_roll(roll);
+        camera = camera.with_motion(view, end);
+    }
+    Ok(Box::new(camera))
+}
+
+// Settings for the different kinds of camera, only some of which apply to
+// each.
+struct CameraOptions {
+    field_of_view: Option<FieldOfView>,
+    sensor: f64,
+    focal_length: f64,
+    fstop: f64,
+    focus: Option<f64>,
+    height: f64,
+    mapping: Mapping,
+    aperture: Aperture,
+    cat_eye: f64,
+    shift: (f64, f64),
+    tilt: f64,
+    swing: f64,
+    lens: Vec<LensElement>,
+}
+
+impl Default for CameraOptions {
+    fn default() -> Self {
+        CameraOptions {
+            field_of_view: None,
+            sensor: 0.024,
+            focal_length: 0.040,
+            fstop: 1.4,
+            focus: None,
+            height: 10.0,
+            mapping: Mapping::Equidistant,
+            aperture: Aperture::Circle,
+            cat_eye: 0.0,
+            shift: (0.0, 0.0),
+            tilt: 0.0,
+            swing: 0.0,
+            lens: vec![],
+        }
+    }
+}
+
+impl CameraOptions {
+    fn build(&self, kind: &str, view: View) -> Result<Box<dyn Camera>, String> {
+        Ok(match kind {
+            "thin_lens" => {
+                let focus = self.focus.unwrap_or(10.0);
+                let mut camera = ThinLens::with_view(view, self.sensor, self.focal_length, focus, self.fstop)
+                    .with_aperture(self.aperture.clone())
+                    .with_cat_eye(self.cat_eye)
+                    .with_shift(self.shift.0, self.shift.1)
+                    .with_tilt(self.tilt, self.swing);
+                if let Some(field_of_view) = self.field_of_view {
+                    camera = camera.with_field_of_view(field_of_view);
+                }
+                Box::new(camera)
+            }
+            "realistic" => {
+                let focus = self.focus.unwrap_or(10.0);
+                Box::new(RealisticLens::new(view, self.lens.clone(), self.sensor, focus)?)
+            }
+            "orthographic" => Box::new(Orthographic::new(view, self.height)),
+            "fisheye" => {
+                let angle = match self.field_of_view {
+                    Some(FieldOfView::Vertical(angle)) | Some(FieldOfView::Horizontal(angle)) => angle,
+                    None => 180.0,
+                };
+                Box::new(Fisheye::new(view, angle, self.mapping))
+            }
+            "equirectangular" => Box::new(Equirectangular::new(view)),
+            "cubemap" => Box::new(Cubemap::new(view)),
+            _ => return Err(format!("unknown camera '{}'", kind)),
+        })
+    }
+}
+
+fn parse_shape(shape: &str, tokens: &mut Tokens, materials: &HashMap<String, Material>) -> Result<Box<dyn Shape>, String> {
+    Ok(match shape {
+        "sphere" => {
+            let (centre, radius) = (tokens.point()?, tokens.number()?);
+            Box::new(Sphere::new(centre, radius, tokens.material(materials)?))
+        }
+        "plane" => {
+            let (point, normal) = (tokens.point()?, tokens.vector()?);
+            Box::new(Plane::new(point, normal, tokens.material(materials)?))
+        }
+        "cuboid" => {
+            let (min, max) = (tokens.point()?, tokens.point()?);
+            Box::new(Cuboid::new(min, max, tokens.material(materials)?))
+        }
+        "rectangle" => {
+            let (min, max) = (tokens.point()?, tokens.point()?);
+            Box::new(Rectangle::axis_aligned(min, max, tokens.material(materials)?))
+        }
+        "quad" => {
+            let (corner, edge_u, edge_v) = (tokens.point()?, tokens.vector()?, tokens.vector()?);
+            Box::new(Rectangle::new(corner, edge_u, edge_v, tokens.material(materials)?))
+        }
+        "disk" => {
+            let (centre, normal, radius) = (tokens.point()?, tokens.vector()?, tokens.number()?);
+            Box::new(Disk::new(centre, normal, radius, tokens.material(materials)?))
+        }
+        _ => return Err(tokens.error(&format!("unknown statement '{}'", shape))),
+    })
+}
+
+// The words of one statement, read from the front.
+struct Tokens<'a> {
+    line: usize,
+    words: &'a [&'a str],
+}
+
+impl<'a> Tokens<'a> {
+    fn new(line: usize, words: &'a [&'a str]) -> Self {
+        Tokens { line, words }
+    }
+
+    fn error(&self, message: &str) -> String {
+        format!("line {}: {}", self.line, message)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    fn finish(&self) -> Result<(), String> {
+        match self.words.first() {
+            None => Ok(()),
+            Some(word) => Err(self.error(&format!("unexpected '{}'", word))),
+        }
+    }
+
+    fn word(&mut self) -> Result<&'a str, String> {
+        let (first, rest) = self
+            .words
+            .split_first()
+            .ok_or_else(|| self.error("statement ends too soon"))?;
+        self.words = rest;
+        Ok(first)
+    }
+
+    fn peek_number(&self) -> Option<f64> {
+        self.words.first().and_then(|word| word.parse().ok())
+    }
+
+    // Infinities and NaN parse as numbers but mean nothing in a scene.
+    fn number(&mut self) -> Result<f64, String> {
+        let word = self.word()?;
+        match word.parse::<f64>() {
+            Ok(number) if number.is_finite() => Ok(number),
+            _ => Err(self.error(&format!("expected a number, found '{}'", word))),
+        }
+    }
+
+    fn vector(&mut self) -> Result<Vector3<f64>, String> {
+        Ok(Vector3::new(self.number()?, self.number()?, self.number()?))
+    }
+
+    fn point(&mut self) -> Result<Point3<f64>, String> {
+        Ok(Point3::from(self.vector()?))
+    }
+
+    // Either three numbers or a single grey level.
+    fn colour(&mut self) -> Result<Vector3<f64>, String> {
+        let first = self.number()?;
+        if self.words.len() >= 2 && self.words[..2].iter().all(|word| word.parse::<f64>().is_ok()) {
+            Ok(Vector3::new(first, self.number()?, self.number()?))
+        } else {
+            Ok(Vector3::new(first, first, first))
+        }
+    }
+
+    fn value(&mut self, nodes: &HashMap<String, usize>) -> Result<Value, String> {
+        if self.peek_number().is_some() {
+            return Ok(Value::Fixed(self.colour()?));
+        }
+        let name = self.word()?;
+        nodes
+            .get(name)
+            .map(|node| Value::Node(*node))
+            .ok_or_else(|| self.error(&format!("unknown node '{}'", name)))
+    }
+
+    // A node input, where plain numbers become constant nodes.
+    fn input(&mut self, graph: &mut NodeGraph, nodes: &HashMap<String, usize>) -> Result<usize, String> {
+        match self.value(nodes)? {
+            Value::Node(node) => Ok(node),
+            Value::Fixed(value) => graph
+                .add(Node::Constant(value))
+                .ok_or_else(|| self.error("couldn't add a constant")),
+        }
+    }
+
+    fn material(&mut self, materials: &HashMap<String, Material>) -> Result<Material, String> {
+        let name = self.word()?;
+        materials
+            .get(name)
+            .cloned()
+            .ok_or_else(|| self.error(&format!("unknown material '{}'", name)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ray::Ray;
+
+    #[test]
+    fn parses_shaded_materials_and_reports_mistakes() {
+        let scene = parse_scene(
+            "
+            node stripes checker 4 0 1   # alternating squares
+            node paint mix 0.9 0.1 0.1  0.1 0.2 0.8  stripes
+            material tiles color paint gloss 0.5 roughness 0.3
+            material gold conductor gold gloss 0.9
+            sphere 0 0 -5 1 tiles
+            plane 0 -1 0  0 1 0 gold
+            ",
+        )
+        .unwrap();
+        let ray = Ray {
+            origin: Point3::new(0.0, 0.0, 0.0),
+            direction: Vector3::new(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+        assert!((scene.intersect(&ray).unwrap().distance - 4.0).abs() < 1e-6);
+
+        assert_eq!(
+            parse_scene("material tiles color missing").err(),
+            Some("line 1: unknown node 'missing'".to_string())
+        );
+        assert!(parse_scene("sphere 0 0 -5 1 tiles").is_err());
+        assert!(parse_scene("material gold conductor mithril").is_err());
+        assert_eq!(
+            parse_scene("node r ramp 0.5 nan 1 1 1 0 0 0 0").err(),
+            Some("line 1: expected a number, found 'nan'".to_string())
+        );
+        assert!(parse_scene("sphere 0 0 -5 inf tiles").is_err());
+        assert_eq!(
+            parse_scene("camera eye 0 1 0 target 0 1 0").err(),
+            Some("line 1: camera eye and target are the same point".to_string())
+        );
+        assert!(parse_scene("camera orthographic eye 0 10 0 target 0 0 0 height 4").is_ok());
+        assert_eq!(
+            parse_scene("camera pinhole eye 0 0 0 target 0 0 -1").err(),
+            Some("line 1: unknown camera 'pinhole'".to_string())
+        );
+        assert_eq!(
+            parse_scene("camera realistic eye 0 0 0 target 0 0 -1").err(),
+            Some("line 1: the lens has no elements".to_string())
+        );
+        assert!(parse_scene("camera realistic eye 0 0 0 target 0 0 -2 lens dgauss").is_ok());
+        for camera in &["camera realistic", "camera realistic stereo side_by_side"] {
+            assert_eq!(
+                parse_scene(&format!("{} eye 0 0 0 target 0 0 -1 lens dgauss focus 0.01", camera)).err(),
+                Some("line 1: the lens can't focus at 0.01".to_string())
+            );
+        }
+        assert_eq!(
+            parse_scene("camera eye 0 0 0 target 0 0 -1 bokeh 1e10 1e10 1").err(),
+            Some("line 1: bokeh image is too large".to_string())
+        );
+        let star = |_: &str| Ok(b"P2 3 3 255  0 255 0  255 255 255  0 255 0".to_vec());
+        assert!(parse_scene_with("camera eye 0 0 0 target 0 0 -1 bokeh_image star.pgm", 0.0, &star).is_ok());
+        assert!(!parse_scene(include_str!("../scenes/nodes.scene")).unwrap().is_spectral());
+        assert!(parse_scene(include_str!("../scenes/caustics.scene")).unwrap().is_spectral());
+        assert!(parse_scene("spectral please").is_err());
+    }
+
+    #[test]
+    fn media_read_voxel_grids_through_the_reader() {
+        let read = |path: &str| std::fs::read(std::path::Path::new("scenes").join(path)).map_err(|error| error.to_string());
+        let scene = parse_scene_with(include_str!("../scenes/smoke.scene"), 0.0, &read).unwrap();
+        let ray = Ray {
+            origin: Point3::new(2.5, 0.0, 0.0),
+            direction: Vector3::new(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+        let boundary = scene.intersect(&ray).unwrap();
+        assert!((boundary.distance - 4.5).abs() < 1e-6);
+        assert!(boundary.material.is_null());
+
+        let missing = |_: &str| Err("no such file".to_string());
+        assert_eq!(
+            parse_scene_with("material smoke scattering 1 density grid plume.voxl", 0.0, &missing).err(),
+            Some("line 1: no such file".to_string())
+        );
+        let garbage = |_: &str| Ok(vec![0u8; 64]);
+        assert!(parse_scene_with("material smoke scattering 1 density grid plume.voxl", 0.0, &garbage).is_err());
+    }
+
+    #[test]
+    fn measured_materials_read_merl_files_through_the_reader() {
+        // A table reflecting the same in every direction.
+        let flat = |_: &str| {
+            let mut bytes = vec![];
+            for dimension in &[90i32, 90, 180] {
+                bytes.extend_from_slice(&dimension.to_le_bytes());
+            }
+            for scale in &[1500.0, 1500.0 / 1.15, 1500.0 / 1.66] {
+                let value = 0.5 / std::f64::consts::PI * scale;
+                for _ in 0..90 * 90 * 180 {
+                    bytes.extend_from_slice(&f64::to_le_bytes(value));
+                }
+            }
+            Ok(bytes)
+        };
+        let scene = parse_scene_with(include_str!("../scenes/measured.scene"), 0.0, &flat).unwrap();
+        let ray = Ray {
+            origin: Point3::new(-1.6, -1.8, 0.0),
+            direction: Vector3::new(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+        let hit = scene.intersect(&ray).unwrap();
+        let normal = Vector3::new(0.0, 0.0, 1.0);
+        let response = hit.material.diffuse_response(&normal, &normal, &normal);
+        assert!((response - Vector3::new(0.5, 0.5, 0.5) / std::f64::consts::PI).amax() < 1e-6);
+
+        let garbage = |_: &str| Ok(vec![0u8; 64]);
+        assert_eq!(
+            parse_scene_with("material paint measured paint.binary", 0.0, &garbage).err(),
+            Some("line 1: 'paint.binary' isn't a MERL BRDF".to_string())
+        );
+    }
+}
@@ -1,14 +1,45 @@
 use nalgebra::{Vector3, Point3};
+use std::rc::Rc;
 
 use crate::sphere::Sphere;
-use crate::material::Material;
+use crate::plane::Plane;
+use crate::rectangle::Rectangle;
+use crate::disk::Disk;
+use crate::cuboid::Cuboid;
+use crate::cylinder::Cylinder;
+use crate::cone::Cone;
+use crate::csg::Csg;
+use crate::torus::Torus;
+use crate::triangle::Triangle;
+use crate::sdf::{Sdf, SdfObject};
+use crate::shape::Shape;
+use crate::spectrum::Dispersion;
+use crate::texture::Texture;
+use crate::thin_film::ThinFilm;
+use crate::material::{Emission, Material};
+use crate::medium::{Density, Medium};
+use crate::volume::{Noise, VoxelGrid};
 use crate::scene::Scene;
-use crate::camera::Camera;
+use crate::camera::ThinLens;
+use crate::conductor::Conductor;
+
+pub use crate::scene_file::{parse_scene, parse_scene_at, parse_scene_with};
 
 pub fn load_scene(name: &str) -> Option<Scene> {
   match name {
     "box" => Some(load_box_scene()),
     "spheres" => Some(load_spheres_scene()),
+    "shapes" => Some(load_shapes_scene()),
+    "cornell" => Some(load_cornell_scene()),
+    "csg" => Some(load_csg_scene()),
+    "sdf" => Some(load_sdf_scene()),
+    "fog" => Some(load_fog_scene()),
+    "clouds" => Some(load_clouds_scene()),
+    "subsurface" => Some(load_subsurface_scene()),
+    "iridescent" => Some(load_iridescent_scene()),
+    "layers" => Some(load_layers_scene()),
+    "foliage" => Some(load_foliage_scene()),
+    "nodes" => parse_scene(include_str!("../scenes/nodes.scene")).ok(),
     _ => None
   }
 }
@@ -44,45 +75,25 @@ fn load_spheres_scene() -> Scene {
         0.2
     );
 
-    let red_plastic = Material::new(
-        Vector3::new(1.0, 0.0, 0.0),
-        1.0,
-        0.0,
-        Vector3::new(0.0, 0.0, 0.0),
-        Vector3::new(0.04, 0.04, 0.04),
-        0.0,
-        0.2
-    );
-
     let silver = Material::new(
-        Vector3::new(0.972, 0.960, 0.915),
+        Vector3::new(0.0, 0.0, 0.0),
         1.0,
         0.0,
         Vector3::new(0.0, 0.0, 0.0),
-        Vector3::new(0.972, 0.960, 0.915),
-        0.9,
+        Vector3::new(0.0, 0.0, 0.0),
+        1.0,
         1.0
-    );
+    ).with_conductor(Conductor::named("silver").unwrap());
 
     let gold = Material::new(
         Vector3::new(0.0, 0.0, 0.0),
         1.0,
         0.0,
         Vector3::new(0.0, 0.0, 0.0),
-        Vector3::new(1.022, 0.782, 0.344),
-        1.0,
-        0.7
-    );
-
-    let glass = Material::new(
         Vector3::new(0.0, 0.0, 0.0),
-        1.6,
         1.0,
-        Vector3::new(0.0, 0.0, 0.0),
-        Vector3::new(0.04, 0.04, 0.04),
-        0.0,
-        0.0
-    );
+        0.7
+    ).with_conductor(Conductor::named("gold").unwrap());
 
     let green_glass = Material::new(
         Vector3::new(0.0, 1.0, 0.0),
@@ -94,16 +105,16 @@ fn load_spheres_scene() -> Scene {
         1.0
     );
 
-    let objects = vec![
-        Sphere::new(Point3::new(-3.3, 1.0, -4.3), 1.0, gold),
-        Sphere::new(Point3::new(-1.1, 1.0, -5.0), 1.0, blue_plastic),
-        Sphere::new(Point3::new(1.0, 1.0, -5.0), 1.0, silver),
-        Sphere::new(Point3::new(3.2, 1.0, -4.6), 1.0, green_glass),
-        Sphere::new(Point3::new(0.5, -1000.0, -8.0), 1000.0, white_lambert),
-        Sphere::new(Point3::new(-8.0, 3.0, -1.0), 2.0, bright_light)
+    let objects: Vec<Box<dyn Shape>> = vec![
+        Box::new(Sphere::new(Point3::new(-3.3, 1.0, -4.3), 1.0, gold)),
+        Box::new(Sphere::new(Point3::new(-1.1, 1.0, -5.0), 1.0, blue_plastic)),
+        Box::new(Sphere::new(Point3::new(1.0, 1.0, -5.0), 1.0, silver)),
+        Box::new(Sphere::new(Point3::new(3.2, 1.0, -4.6), 1.0, green_glass)),
+        Box::new(Plane::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0), white_lambert)),
+        Box::new(Sphere::new(Point3::new(-8.0, 3.0, -1.0), 2.0, bright_light))
     ];
 
-    let camera = Camera::new(
+    let camera = ThinLens::new(
         Point3::new(0.0, 6.0, 8.0),
         0.024,
         0.055,
@@ -158,37 +169,199 @@ fn load_box_scene() -> Scene {
     );
 
     let silver = Material::new(
-        Vector3::new(0.972, 0.960, 0.915),
+        Vector3::new(0.0, 0.0, 0.0),
         1.0,
         0.0,
         Vector3::new(0.0, 0.0, 0.0),
-        Vector3::new(0.972, 0.960, 0.915),
-        0.9,
+        Vector3::new(0.0, 0.0, 0.0),
+        1.0,
         1.0
-    );
+    ).with_conductor(Conductor::named("silver").unwrap());
 
+    // A strongly dispersive flint glass, which splits light into rainbows
+    // when rendering spectrally.
     let glass = Material::new(
         Vector3::new(0.0, 0.0, 0.0),
-        1.6,
+        1.78,
+        1.0,
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.04, 0.04, 0.04),
+        0.0,
+        0.0
+    ).with_dispersion(Dispersion::sf11());
+
+    let objects: Vec<Box<dyn Shape>> = vec![
+        Box::new(Rectangle::axis_aligned(Point3::new(-5.0, -3.0, -10.0), Point3::new(-5.0, 3.0, 8.0), blue_plastic)),
+        Box::new(Rectangle::axis_aligned(Point3::new(5.0, -3.0, -10.0), Point3::new(5.0, 3.0, 8.0), red_plastic)),
+        Box::new(Rectangle::axis_aligned(Point3::new(-5.0, -3.0, -10.0), Point3::new(5.0, -3.0, 8.0), white_lambert.clone())),
+        Box::new(Rectangle::axis_aligned(Point3::new(-5.0, 3.0, -10.0), Point3::new(5.0, 3.0, 8.0), white_lambert.clone())),
+        Box::new(Rectangle::axis_aligned(Point3::new(-5.0, -3.0, -10.0), Point3::new(5.0, 3.0, -10.0), white_lambert)),
+        Box::new(Sphere::new(Point3::new(0.0, 13.0, -8.0), 10.5, bright_light)),
+        Box::new(Sphere::new(Point3::new(1.0, -2.0, -7.0), 1.0, silver)),
+        Box::new(Sphere::new(Point3::new(-0.75, -2.0, -5.0), 1.0, glass))
+    ];
+
+    let camera = ThinLens::new(
+        Point3::new(0.0, 0.0, 7.0),
+        0.024,
+        0.040,
+        15.0,
+        1.4,
+        0.0,
+        0.0
+    );
+
+    Scene::new(objects, camera)
+}
+
+fn load_shapes_scene() -> Scene {
+  let bright_light = Material::new(
+        Vector3::new(0.0, 0.0, 0.0),
+        1.0,
+        1.0,
+        Vector3::new(3000.0, 3000.0, 3000.0),
+        Vector3::new(0.0, 0.0, 0.0),
+        0.0,
+        0.0
+    );
+
+    let white_lambert = Material::new(
+        Vector3::new(1.0, 1.0, 1.0),
         1.0,
+        0.0,
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.03, 0.03, 0.03),
+        0.0,
+        0.0
+    );
+
+    let blue_plastic = Material::new(
+        Vector3::new(0.1, 0.1, 1.0),
+        1.0,
+        0.0,
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.04, 0.04, 0.04),
+        0.0,
+        0.2
+    );
+
+    let red_plastic = Material::new(
+        Vector3::new(1.0, 0.0, 0.0),
+        1.0,
+        0.0,
         Vector3::new(0.0, 0.0, 0.0),
         Vector3::new(0.04, 0.04, 0.04),
         0.0,
+        0.2
+    );
+
+    let silver = Material::new(
+        Vector3::new(0.0, 0.0, 0.0),
+        1.0,
+        0.0,
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.0, 0.0, 0.0),
+        1.0,
+        1.0
+    ).with_conductor(Conductor::named("silver").unwrap());
+
+    let glowing_sign = Material::new(
+        Vector3::new(0.0, 0.0, 0.0),
+        1.0,
+        0.0,
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.0, 0.0, 0.0),
+        0.0,
+        0.0
+    ).emitting(Emission::Radiance(Vector3::new(20.0, 12.0, 4.0)), true);
+
+    let objects: Vec<Box<dyn Shape>> = vec![
+        Box::new(Cuboid::new(Point3::new(-4.5, 0.0, -6.0), Point3::new(-2.5, 2.0, -4.0), red_plastic.clone())),
+        Box::new(Cylinder::new(Point3::new(-1.0, 0.0, -5.0), Vector3::new(0.0, 1.0, 0.0), 0.8, 2.0, blue_plastic)),
+        Box::new(Cone::new(Point3::new(1.2, 0.0, -5.0), Vector3::new(0.0, 1.0, 0.0), 0.9, 2.2, white_lambert.clone())),
+        Box::new(Torus::new(Point3::new(3.1, 1.0, -5.5), Vector3::new(0.0, 0.4, 1.0), 0.9, 0.3, silver)),
+        Box::new(Disk::new(Point3::new(0.0, 0.01, -2.5), Vector3::new(0.0, 1.0, 0.0), 1.5, red_plastic)),
+        Box::new(Triangle::new(
+            Point3::new(-4.5, 0.0, -2.0),
+            Point3::new(-2.5, 0.0, -2.0),
+            Point3::new(-3.5, 1.6, -2.5),
+            glowing_sign
+        )),
+        Box::new(Plane::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0), white_lambert)),
+        Box::new(Sphere::new(Point3::new(-8.0, 3.0, -1.0), 2.0, bright_light))
+    ];
+
+    let camera = ThinLens::new(
+        Point3::new(0.0, 6.0, 8.0),
+        0.024,
+        0.055,
+        14.0,
+        1.4,
+        0.0,
+        25.0
+    );
+
+    Scene::new(objects, camera)
+}
+
+fn load_cornell_scene() -> Scene {
+  let ceiling_light = Material::new(
+        Vector3::new(0.0, 0.0, 0.0),
+        1.0,
+        0.0,
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.0, 0.0, 0.0),
+        0.0,
+        0.0
+    ).emitting(Emission::Power(Vector3::new(150000.0, 140000.0, 120000.0)), false);
+
+    let white_lambert = Material::new(
+        Vector3::new(1.0, 1.0, 1.0),
+        1.0,
+        0.0,
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.03, 0.03, 0.03),
+        0.0,
+        0.0
+    );
+
+    let green_lambert = Material::new(
+        Vector3::new(0.1, 0.8, 0.1),
+        1.0,
+        0.0,
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.03, 0.03, 0.03),
+        0.0,
+        0.0
+    );
+
+    let red_lambert = Material::new(
+        Vector3::new(0.8, 0.1, 0.1),
+        1.0,
+        0.0,
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.03, 0.03, 0.03),
+        0.0,
         0.0
     );
 
-    let objects = vec![
-        Sphere::new(Point3::new(-1005.0, 0.0, -8.0), 1000.0, blue_plastic),
-        Sphere::new(Point3::new(1005.0, 0.0, -8.0), 1000.0, red_plastic),
-        Sphere::new(Point3::new(0.0, -1003.0, -8.0), 1000.0, white_lambert),
-        Sphere::new(Point3::new(0.0, 1003.0, -8.0), 1000.0, white_lambert),
-        Sphere::new(Point3::new(0.0, 0.0, -1010.0), 1000.0, white_lambert),
-        Sphere::new(Point3::new(0.0, 13.0, -8.0), 10.5, bright_light),
-        Sphere::new(Point3::new(1.0, -2.0, -7.0), 1.0, silver),
-        Sphere::new(Point3::new(-0.75, -2.0, -5.0), 1.0, glass)
+    let objects: Vec<Box<dyn Shape>> = vec![
+        Box::new(Rectangle::axis_aligned(Point3::new(-5.0, -3.0, -10.0), Point3::new(-5.0, 3.0, 8.0), red_lambert)),
+        Box::new(Rectangle::axis_aligned(Point3::new(5.0, -3.0, -10.0), Point3::new(5.0, 3.0, 8.0), green_lambert)),
+        Box::new(Rectangle::axis_aligned(Point3::new(-5.0, -3.0, -10.0), Point3::new(5.0, -3.0, 8.0), white_lambert.clone())),
+        Box::new(Rectangle::axis_aligned(Point3::new(-5.0, 3.0, -10.0), Point3::new(5.0, 3.0, 8.0), white_lambert.clone())),
+        Box::new(Rectangle::axis_aligned(Point3::new(-5.0, -3.0, -10.0), Point3::new(5.0, 3.0, -10.0), white_lambert.clone())),
+        Box::new(Rectangle::new(
+            Point3::new(-1.5, 2.99, -8.0),
+            Vector3::new(3.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, 3.0),
+            ceiling_light
+        )),
+        Box::new(Cuboid::new(Point3::new(-3.5, -3.0, -8.5), Point3::new(-1.0, 1.0, -6.0), white_lambert.clone())),
+        Box::new(Cuboid::new(Point3::new(0.5, -3.0, -6.0), Point3::new(3.0, -1.0, -3.5), white_lambert))
     ];
 
-    let camera = Camera::new(
+    let camera = ThinLens::new(
         Point3::new(0.0, 0.0, 7.0),
         0.024,
         0.040,
@@ -199,4 +372,723 @@ fn load_box_scene() -> Scene {
     );
 
     Scene::new(objects, camera)
-}
\ No newline at end of file
+}
+
+fn load_csg_scene() -> Scene {
+  let bright_light = Material::new(
+        Vector3::new(0.0, 0.0, 0.0),
+        1.0,
+        1.0,
+        Vector3::new(3000.0, 3000.0, 3000.0),
+        Vector3::new(0.0, 0.0, 0.0),
+        0.0,
+        0.0
+    );
+
+    let white_lambert = Material::new(
+        Vector3::new(1.0, 1.0, 1.0),
+        1.0,
+        0.0,
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.03, 0.03, 0.03),
+        0.0,
+        0.0
+    );
+
+    let blue_plastic = Material::new(
+        Vector3::new(0.1, 0.1, 1.0),
+        1.0,
+        0.0,
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.04, 0.04, 0.04),
+        0.0,
+        0.2
+    );
+
+    let glass = Material::new(
+        Vector3::new(0.0, 0.0, 0.0),
+        1.6,
+        1.0,
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.04, 0.04, 0.04),
+        0.0,
+        0.0
+    );
+
+    // A biconvex lens is the overlap of two spheres.
+    let lens = Csg::intersection(
+        Box::new(Sphere::new(Point3::new(-3.0, 1.5, -3.0), 2.0, glass.clone())),
+        Box::new(Sphere::new(Point3::new(-3.0, 1.5, -6.0), 2.0, glass.clone())),
+        glass.clone()
+    );
+
+    // A bowl is a hollow shell cut in half by a plane, standing on a foot.
+    let bowl = Csg::union(
+        Box::new(Csg::intersection(
+            Box::new(Csg::difference(
+                Box::new(Sphere::new(Point3::new(0.0, 1.5, -5.0), 1.3, blue_plastic.clone())),
+                Box::new(Sphere::new(Point3::new(0.0, 1.5, -5.0), 1.15, blue_plastic.clone())),
+                blue_plastic.clone()
+            )),
+            Box::new(Plane::new(Point3::new(0.0, 1.5, -5.0), Vector3::new(0.0, 1.0, 0.0), blue_plastic.clone())),
+            blue_plastic.clone()
+        )),
+        Box::new(Cylinder::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 1.0, 0.0), 0.5, 0.3, blue_plastic.clone())),
+        blue_plastic
+    );
+
+    let shell = Csg::difference(
+        Box::new(Sphere::new(Point3::new(3.0, 1.0, -5.0), 1.0, glass.clone())),
+        Box::new(Sphere::new(Point3::new(3.0, 1.0, -5.0), 0.9, glass.clone())),
+        glass
+    );
+
+    let objects: Vec<Box<dyn Shape>> = vec![
+        Box::new(lens),
+        Box::new(bowl),
+        Box::new(shell),
+        Box::new(Plane::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0), white_lambert)),
+        Box::new(Sphere::new(Point3::new(-8.0, 3.0, -1.0), 2.0, bright_light))
+    ];
+
+    let camera = ThinLens::new(
+        Point3::new(0.0, 6.0, 8.0),
+        0.024,
+        0.055,
+        14.0,
+        1.4,
+        0.0,
+        25.0
+    );
+
+    Scene::new(objects, camera)
+}
+
+fn load_sdf_scene() -> Scene {
+  let bright_light = Material::new(
+        Vector3::new(0.0, 0.0, 0.0),
+        1.0,
+        1.0,
+        Vector3::new(3000.0, 3000.0, 3000.0),
+        Vector3::new(0.0, 0.0, 0.0),
+        0.0,
+        0.0
+    );
+
+    let white_lambert = Material::new(
+        Vector3::new(1.0, 1.0, 1.0),
+        1.0,
+        0.0,
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.03, 0.03, 0.03),
+        0.0,
+        0.0
+    );
+
+    let blue_plastic = Material::new(
+        Vector3::new(0.1, 0.1, 1.0),
+        1.0,
+        0.0,
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.04, 0.04, 0.04),
+        0.0,
+        0.2
+    );
+
+    let red_plastic = Material::new(
+        Vector3::new(1.0, 0.0, 0.0),
+        1.0,
+        0.0,
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.04, 0.04, 0.04),
+        0.0,
+        0.2
+    );
+
+    let gold = Material::new(
+        Vector3::new(0.0, 0.0, 0.0),
+        1.0,
+        0.0,
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.0, 0.0, 0.0),
+        1.0,
+        0.7
+    ).with_conductor(Conductor::named("gold").unwrap());
+
+    let blob = Sdf::sphere(0.9)
+        .smooth_union(Sdf::capsule(Point3::new(-0.6, -0.9, 0.0), Point3::new(0.8, 0.6, 0.3), 0.35), 0.4)
+        .smooth_subtraction(Sdf::sphere(0.5).translate(Vector3::new(0.0, 0.3, 0.8)), 0.2)
+        .translate(Vector3::new(-3.0, 1.0, -5.0));
+
+    let twisted = Sdf::cuboid(Vector3::new(0.6, 1.2, 0.6))
+        .twist(1.2)
+        .translate(Vector3::new(0.0, 1.2, -5.0));
+
+    let rings = Sdf::torus(0.3, 0.08)
+        .repeat(Vector3::new(0.8, 0.0, 0.0))
+        .translate(Vector3::new(0.0, 0.08, -2.0));
+
+    let bulb = Sdf::mandelbulb(8.0, 6).translate(Vector3::new(3.0, 1.2, -5.0));
+
+    let objects: Vec<Box<dyn Shape>> = vec![
+        Box::new(SdfObject::new(blob, blue_plastic)),
+        Box::new(SdfObject::new(twisted, red_plastic).with_step_scale(0.5)),
+        Box::new(SdfObject::new(bulb, gold)),
+        Box::new(SdfObject::new(rings, white_lambert.clone())),
+        Box::new(Plane::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0), white_lambert)),
+        Box::new(Sphere::new(Point3::new(-8.0, 3.0, -1.0), 2.0, bright_light))
+    ];
+
+    let camera = ThinLens::new(
+        Point3::new(0.0, 6.0, 8.0),
+        0.024,
+        0.055,
+        14.0,
+        1.4,
+        0.0,
+        25.0
+    );
+
+    Scene::new(objects, camera)
+}
+
+fn load_fog_scene() -> Scene {
+  let ceiling_light = Material::new(
+        Vector3::new(0.0, 0.0, 0.0),
+        1.0,
+        0.0,
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.0, 0.0, 0.0),
+        0.0,
+        0.0
+    ).emitting(Emission::Power(Vector3::new(150000.0, 140000.0, 120000.0)), false);
+
+    let white_lambert = Material::new(
+        Vector3::new(1.0, 1.0, 1.0),
+        1.0,
+        0.0,
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.03, 0.03, 0.03),
+        0.0,
+        0.0
+    );
+
+    let smoky_glass = Material::new(
+        Vector3::new(0.0, 0.0, 0.0),
+        1.5,
+        1.0,
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.04, 0.04, 0.04),
+        0.0,
+        1.0
+    ).with_interior(Medium::new(
+        Vector3::new(0.05, 0.2, 0.4),
+        Vector3::new(0.3, 0.3, 0.3),
+        0.0
+    ));
+
+    // Only marks out the boundary of the smoke inside it.
+    let smoke = Material::new(
+        Vector3::new(0.0, 0.0, 0.0),
+        1.0,
+        1.0,
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.0, 0.0, 0.0),
+        0.0,
+        0.0
+    ).with_interior(Medium::new(
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(1.5, 1.5, 1.5),
+        0.6
+    ));
+
+    let objects: Vec<Box<dyn Shape>> = vec![
+        Box::new(Rectangle::axis_aligned(Point3::new(-5.0, -3.0, -10.0), Point3::new(-5.0, 3.0, 8.0), white_lambert.clone())),
+        Box::new(Rectangle::axis_aligned(Point3::new(5.0, -3.0, -10.0), Point3::new(5.0, 3.0, 8.0), white_lambert.clone())),
+        Box::new(Rectangle::axis_aligned(Point3::new(-5.0, -3.0, -10.0), Point3::new(5.0, -3.0, 8.0), white_lambert.clone())),
+        Box::new(Rectangle::axis_aligned(Point3::new(-5.0, 3.0, -10.0), Point3::new(5.0, 3.0, 8.0), white_lambert.clone())),
+        Box::new(Rectangle::axis_aligned(Point3::new(-5.0, -3.0, -10.0), Point3::new(5.0, 3.0, -10.0), white_lambert)),
+        Box::new(Rectangle::new(
+            Point3::new(-1.5, 2.99, -8.0),
+            Vector3::new(3.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, 3.0),
+            ceiling_light
+        )),
+        Box::new(Sphere::new(Point3::new(-2.2, -1.5, -6.5), 1.5, smoky_glass)),
+        Box::new(Sphere::new(Point3::new(2.2, -1.5, -5.5), 1.5, smoke))
+    ];
+
+    let camera = ThinLens::new(
+        Point3::new(0.0, 0.0, 7.0),
+        0.024,
+        0.040,
+        15.0,
+        1.4,
+        0.0,
+        0.0
+    );
+
+    Scene::new(objects, camera).with_medium(Medium::new(
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.02, 0.02, 0.02),
+        0.3
+    ))
+}
+
+fn load_clouds_scene() -> Scene {
+  let ceiling_light = Material::new(
+        Vector3::new(0.0, 0.0, 0.0),
+        1.0,
+        0.0,
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.0, 0.0, 0.0),
+        0.0,
+        0.0
+    ).emitting(Emission::Power(Vector3::new(150000.0, 140000.0, 120000.0)), false);
+
+    let white_lambert = Material::new(
+        Vector3::new(1.0, 1.0, 1.0),
+        1.0,
+        0.0,
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.03, 0.03, 0.03),
+        0.0,
+        0.0
+    );
+
+    let volume_boundary = Material::new(
+        Vector3::new(0.0, 0.0, 0.0),
+        1.0,
+        1.0,
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.0, 0.0, 0.0),
+        0.0,
+        0.0
+    );
+
+    let cloud = volume_boundary.clone().with_interior(Medium::new(
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(12.0, 12.0, 12.0),
+        0.8
+    ).with_density(Density::Noise(Noise::new(0.8, 5, 0.5))));
+
+    let plume_min = Point3::new(1.0, -2.99, -7.5);
+    let plume_max = Point3::new(4.0, 2.5, -4.5);
+    let smoke = volume_boundary.with_interior(Medium::new(
+        Vector3::new(6.0, 6.0, 6.0),
+        Vector3::new(20.0, 20.0, 20.0),
+        0.3
+    ).with_density(Density::Grid(Rc::new(smoke_plume(plume_min, plume_max)))));
+
+    let objects: Vec<Box<dyn Shape>> = vec![
+        Box::new(Rectangle::axis_aligned(Point3::new(-5.0, -3.0, -10.0), Point3::new(-5.0, 3.0, 8.0), white_lambert.clone())),
+        Box::new(Rectangle::axis_aligned(Point3::new(5.0, -3.0, -10.0), Point3::new(5.0, 3.0, 8.0), white_lambert.clone())),
+        Box::new(Rectangle::axis_aligned(Point3::new(-5.0, -3.0, -10.0), Point3::new(5.0, -3.0, 8.0), white_lambert.clone())),
+        Box::new(Rectangle::axis_aligned(Point3::new(-5.0, 3.0, -10.0), Point3::new(5.0, 3.0, 8.0), white_lambert.clone())),
+        Box::new(Rectangle::axis_aligned(Point3::new(-5.0, -3.0, -10.0), Point3::new(5.0, 3.0, -10.0), white_lambert)),
+        Box::new(Rectangle::new(
+            Point3::new(-1.5, 2.99, -8.0),
+            Vector3::new(3.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, 3.0),
+            ceiling_light
+        )),
+        Box::new(Cuboid::new(Point3::new(-4.5, -1.0, -8.0), Point3::new(0.5, 1.5, -4.0), cloud)),
+        Box::new(Cuboid::new(plume_min, plume_max, smoke))
+    ];
+
+    let camera = ThinLens::new(
+        Point3::new(0.0, 0.0, 7.0),
+        0.024,
+        0.040,
+        15.0,
+        1.4,
+        0.0,
+        0.0
+    );
+
+    Scene::new(objects, camera)
+}
+
+fn load_subsurface_scene() -> Scene {
+  let ceiling_light = Material::new(
+        Vector3::new(0.0, 0.0, 0.0),
+        1.0,
+        0.0,
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.0, 0.0, 0.0),
+        0.0,
+        0.0
+    ).emitting(Emission::Power(Vector3::new(150000.0, 140000.0, 120000.0)), false);
+
+    let white_lambert = Material::new(
+        Vector3::new(1.0, 1.0, 1.0),
+        1.0,
+        0.0,
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.03, 0.03, 0.03),
+        0.0,
+        0.0
+    );
+
+    let translucent = Material::new(
+        Vector3::new(1.0, 1.0, 1.0),
+        1.0,
+        0.0,
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.04, 0.04, 0.04),
+        0.0,
+        0.8
+    );
+
+    let skin = translucent.clone().with_subsurface(
+        Vector3::new(0.99, 0.9, 0.8),
+        Vector3::new(0.4, 0.15, 0.08)
+    );
+
+    let wax = translucent.clone().with_subsurface(
+        Vector3::new(0.99, 0.95, 0.7),
+        Vector3::new(0.5, 0.4, 0.2)
+    );
+
+    let marble = translucent.with_subsurface(
+        Vector3::new(0.999, 0.998, 0.995),
+        Vector3::new(0.25, 0.25, 0.25)
+    );
+
+    let objects: Vec<Box<dyn Shape>> = vec![
+        Box::new(Rectangle::axis_aligned(Point3::new(-5.0, -3.0, -10.0), Point3::new(-5.0, 3.0, 8.0), white_lambert.clone())),
+        Box::new(Rectangle::axis_aligned(Point3::new(5.0, -3.0, -10.0), Point3::new(5.0, 3.0, 8.0), white_lambert.clone())),
+        Box::new(Rectangle::axis_aligned(Point3::new(-5.0, -3.0, -10.0), Point3::new(5.0, -3.0, 8.0), white_lambert.clone())),
+        Box::new(Rectangle::axis_aligned(Point3::new(-5.0, 3.0, -10.0), Point3::new(5.0, 3.0, 8.0), white_lambert.clone())),
+        Box::new(Rectangle::axis_aligned(Point3::new(-5.0, -3.0, -10.0), Point3::new(5.0, 3.0, -10.0), white_lambert)),
+        Box::new(Rectangle::new(
+            Point3::new(-1.5, 2.99, -8.0),
+            Vector3::new(3.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, 3.0),
+            ceiling_light
+        )),
+        Box::new(Sphere::new(Point3::new(-2.8, -1.6, -6.5), 1.4, skin)),
+        Box::new(Cylinder::new(Point3::new(0.0, -3.0, -7.5), Vector3::new(0.0, 1.0, 0.0), 0.8, 3.0, wax)),
+        Box::new(Cuboid::new(Point3::new(1.8, -3.0, -6.5), Point3::new(3.8, -1.0, -4.5), marble))
+    ];
+
+    let camera = ThinLens::new(
+        Point3::new(0.0, 0.0, 7.0),
+        0.024,
+        0.040,
+        15.0,
+        1.4,
+        0.0,
+        0.0
+    );
+
+    Scene::new(objects, camera)
+}
+
+fn load_iridescent_scene() -> Scene {
+  let ceiling_light = Material::new(
+        Vector3::new(0.0, 0.0, 0.0),
+        1.0,
+        0.0,
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.0, 0.0, 0.0),
+        0.0,
+        0.0
+    ).emitting(Emission::Power(Vector3::new(150000.0, 140000.0, 120000.0)), false);
+
+    let white_lambert = Material::new(
+        Vector3::new(1.0, 1.0, 1.0),
+        1.0,
+        0.0,
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.03, 0.03, 0.03),
+        0.0,
+        0.0
+    );
+
+    // Soap drains downwards, so the film is thickest at the bottom.
+    let soap_bubble = Material::new(
+        Vector3::new(0.0, 0.0, 0.0),
+        1.0,
+        1.0,
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.0, 0.0, 0.0),
+        0.0,
+        1.0
+    ).with_thin_film(ThinFilm::new(Texture::gradient(250.0, 900.0), 1.33));
+
+    let oil_slick = Material::new(
+        Vector3::new(0.05, 0.05, 0.05),
+        1.0,
+        0.0,
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.02, 0.02, 0.02),
+        0.0,
+        1.0
+    ).with_thin_film(ThinFilm::new(Texture::noise(6.0, 4, 200.0, 700.0), 1.45));
+
+    let anodised_titanium = Material::new(
+        Vector3::new(0.0, 0.0, 0.0),
+        1.0,
+        0.0,
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.54, 0.5, 0.46),
+        1.0,
+        0.9
+    ).with_thin_film(ThinFilm::new(Texture::constant(320.0), 2.3));
+
+    let objects: Vec<Box<dyn Shape>> = vec![
+        Box::new(Rectangle::axis_aligned(Point3::new(-5.0, -3.0, -10.0), Point3::new(-5.0, 3.0, 8.0), white_lambert.clone())),
+        Box::new(Rectangle::axis_aligned(Point3::new(5.0, -3.0, -10.0), Point3::new(5.0, 3.0, 8.0), white_lambert.clone())),
+        Box::new(Rectangle::axis_aligned(Point3::new(-5.0, -3.0, -10.0), Point3::new(5.0, -3.0, 8.0), oil_slick)),
+        Box::new(Rectangle::axis_aligned(Point3::new(-5.0, 3.0, -10.0), Point3::new(5.0, 3.0, 8.0), white_lambert.clone())),
+        Box::new(Rectangle::axis_aligned(Point3::new(-5.0, -3.0, -10.0), Point3::new(5.0, 3.0, -10.0), white_lambert)),
+        Box::new(Rectangle::new(
+            Point3::new(-1.5, 2.99, -8.0),
+            Vector3::new(3.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, 3.0),
+            ceiling_light
+        )),
+        Box::new(Sphere::new(Point3::new(-1.5, -0.5, -5.0), 1.6, soap_bubble)),
+        Box::new(Sphere::new(Point3::new(2.5, -1.8, -6.5), 1.2, anodised_titanium))
+    ];
+
+    let camera = ThinLens::new(
+        Point3::new(0.0, 0.0, 7.0),
+        0.024,
+        0.040,
+        15.0,
+        1.4,
+        0.0,
+        0.0
+    );
+
+    Scene::new(objects, camera)
+}
+
+fn load_layers_scene() -> Scene {
+    let ceiling_light = Material::new(
+        Vector3::new(0.0, 0.0, 0.0),
+        1.0,
+        0.0,
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.0, 0.0, 0.0),
+        0.0,
+        0.0
+    ).emitting(Emission::Power(Vector3::new(150000.0, 140000.0, 120000.0)), false);
+
+    let white_lambert = Material::new(
+        Vector3::new(1.0, 1.0, 1.0),
+        1.0,
+        0.0,
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.03, 0.03, 0.03),
+        0.0,
+        0.0
+    );
+
+    let concrete = Material::new(
+        Vector3::new(0.55, 0.53, 0.5),
+        1.0,
+        0.0,
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.0, 0.0, 0.0),
+        0.0,
+        0.0
+    ).with_diffuse_roughness(1.0);
+
+    let car_paint = Material::new(
+        Vector3::new(0.7, 0.02, 0.03),
+        1.0,
+        0.0,
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.0, 0.0, 0.0),
+        0.0,
+        0.0
+    ).with_clearcoat(1.5, 1.0);
+
+    let velvet = Material::new(
+        Vector3::new(0.05, 0.05, 0.35),
+        1.0,
+        0.0,
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.0, 0.0, 0.0),
+        0.0,
+        0.0
+    ).with_sheen(Vector3::new(0.8, 0.8, 1.0));
+
+    let lacquered_gold = Material::new(
+        Vector3::new(0.0, 0.0, 0.0),
+        1.0,
+        0.0,
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.0, 0.0, 0.0),
+        1.0,
+        0.6
+    ).with_conductor(Conductor::named("gold").unwrap()).with_clearcoat(1.5, 1.0);
+
+    let brushed_aluminium = Material::new(
+        Vector3::new(0.0, 0.0, 0.0),
+        1.0,
+        0.0,
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.0, 0.0, 0.0),
+        1.0,
+        1.0
+    ).with_conductor(Conductor::named("aluminium").unwrap()).with_anisotropy(0.02, 0.4);
+
+    let objects: Vec<Box<dyn Shape>> = vec![
+        Box::new(Rectangle::axis_aligned(Point3::new(-5.0, -3.0, -10.0), Point3::new(-5.0, 3.0, 8.0), white_lambert.clone())),
+        Box::new(Rectangle::axis_aligned(Point3::new(5.0, -3.0, -10.0), Point3::new(5.0, 3.0, 8.0), white_lambert.clone())),
+        Box::new(Rectangle::axis_aligned(Point3::new(-5.0, -3.0, -10.0), Point3::new(5.0, -3.0, 8.0), concrete)),
+        Box::new(Rectangle::axis_aligned(Point3::new(-5.0, 3.0, -10.0), Point3::new(5.0, 3.0, 8.0), white_lambert.clone())),
+        Box::new(Rectangle::axis_aligned(Point3::new(-5.0, -3.0, -10.0), Point3::new(5.0, 3.0, -10.0), white_lambert)),
+        Box::new(Rectangle::new(
+            Point3::new(-1.5, 2.99, -8.0),
+            Vector3::new(3.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, 3.0),
+            ceiling_light
+        )),
+        Box::new(Disk::new(Point3::new(0.0, 0.5, -9.99), Vector3::new(0.0, 0.0, 1.0), 2.0, brushed_aluminium)),
+        Box::new(Sphere::new(Point3::new(-3.0, -1.8, -6.0), 1.2, car_paint)),
+        Box::new(Sphere::new(Point3::new(0.0, -1.8, -6.5), 1.2, velvet)),
+        Box::new(Sphere::new(Point3::new(3.0, -1.8, -6.0), 1.2, lacquered_gold))
+    ];
+
+    let camera = ThinLens::new(
+        Point3::new(0.0, 0.0, 7.0),
+        0.024,
+        0.040,
+        15.0,
+        1.4,
+        0.0,
+        0.0
+    );
+
+    Scene::new(objects, camera)
+}
+
+fn load_foliage_scene() -> Scene {
+    let ceiling_light = Material::new(
+        Vector3::new(0.0, 0.0, 0.0),
+        1.0,
+        0.0,
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.0, 0.0, 0.0),
+        0.0,
+        0.0
+    ).emitting(Emission::Power(Vector3::new(150000.0, 140000.0, 120000.0)), false);
+
+    let white_lambert = Material::new(
+        Vector3::new(1.0, 1.0, 1.0),
+        1.0,
+        0.0,
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.03, 0.03, 0.03),
+        0.0,
+        0.0
+    );
+
+    let leaves = Material::new(
+        Vector3::new(0.15, 0.5, 0.1),
+        1.0,
+        0.0,
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.0, 0.0, 0.0),
+        0.0,
+        0.0
+    ).with_cutout(Texture::noise(6.0, 4, 0.0, 1.0), 0.5);
+
+    let fence = Material::new(
+        Vector3::new(0.6, 0.4, 0.25),
+        1.0,
+        0.0,
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.0, 0.0, 0.0),
+        0.0,
+        0.0
+    ).with_cutout(Texture::checker(8.0, 0.0, 1.0), 0.5);
+
+    let net_curtain = Material::new(
+        Vector3::new(0.9, 0.9, 0.9),
+        1.0,
+        0.0,
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.0, 0.0, 0.0),
+        0.0,
+        0.0
+    ).with_opacity(Texture::constant(0.4));
+
+    let objects: Vec<Box<dyn Shape>> = vec![
+        Box::new(Rectangle::axis_aligned(Point3::new(-5.0, -3.0, -10.0), Point3::new(-5.0, 3.0, 8.0), white_lambert.clone())),
+        Box::new(Rectangle::axis_aligned(Point3::new(5.0, -3.0, -10.0), Point3::new(5.0, 3.0, 8.0), white_lambert.clone())),
+        Box::new(Rectangle::axis_aligned(Point3::new(-5.0, -3.0, -10.0), Point3::new(5.0, -3.0, 8.0), white_lambert.clone())),
+        Box::new(Rectangle::axis_aligned(Point3::new(-5.0, 3.0, -10.0), Point3::new(5.0, 3.0, 8.0), white_lambert.clone())),
+        Box::new(Rectangle::axis_aligned(Point3::new(-5.0, -3.0, -10.0), Point3::new(5.0, 3.0, -10.0), white_lambert)),
+        Box::new(Rectangle::new(
+            Point3::new(-1.5, 2.99, -8.0),
+            Vector3::new(3.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, 3.0),
+            ceiling_light
+        )),
+        Box::new(Rectangle::new(
+            Point3::new(-4.0, -3.0, -7.0),
+            Vector3::new(3.5, 0.0, 0.0),
+            Vector3::new(0.0, 3.5, 1.0),
+            leaves.clone()
+        )),
+        Box::new(Rectangle::new(
+            Point3::new(-3.0, -3.0, -5.5),
+            Vector3::new(2.5, 0.0, -1.0),
+            Vector3::new(0.0, 2.5, 0.0),
+            leaves
+        )),
+        Box::new(Rectangle::new(
+            Point3::new(0.5, -3.0, -8.0),
+            Vector3::new(4.0, 0.0, 0.0),
+            Vector3::new(0.0, 4.0, 0.0),
+            fence
+        )),
+        Box::new(Rectangle::new(
+            Point3::new(1.0, -3.0, -4.0),
+            Vector3::new(3.0, 0.0, -1.0),
+            Vector3::new(0.0, 5.0, 0.0),
+            net_curtain
+        ))
+    ];
+
+    let camera = ThinLens::new(
+        Point3::new(0.0, 0.0, 7.0),
+        0.024,
+        0.040,
+        15.0,
+        1.4,
+        0.0,
+        0.0
+    );
+
+    Scene::new(objects, camera)
+}
+
+// A column of smoke which spreads out and thins as it rises.
+fn smoke_plume(min: Point3<f64>, max: Point3<f64>) -> VoxelGrid {
+    let size = [24, 48, 24];
+    let noise = Noise::new(6.0, 3, 0.3);
+    let mut data = Vec::with_capacity(size[0] * size[1] * size[2]);
+    for z in 0..size[2] {
+        for y in 0..size[1] {
+            for x in 0..size[0] {
+                let local = Vector3::new(
+                    (x as f64 + 0.5) / size[0] as f64,
+                    (y as f64 + 0.5) / size[1] as f64,
+                    (z as f64 + 0.5) / size[2] as f64,
+                );
+                let radius = 0.1 + 0.35 * local.y;
+                let distance = ((local.x - 0.5).powi(2) + (local.z - 0.5).powi(2)).sqrt();
+                let falloff = (1.0 - distance / radius).max(0.0) * (1.0 - local.y);
+                data.push(falloff * noise.density(&Point3::from(local)));
+            }
+        }
+    }
+    VoxelGrid::new(min, max, size, data)
+}
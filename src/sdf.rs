@@ -0,0 +1,434 @@
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::shape::{Shape, Surface};
+use nalgebra::{Point2, Point3, Vector3};
+use std::f64;
+
+const MAX_STEPS: usize = 256;
+const SURFACE_DISTANCE: f64 = 1e-4;
+// Padding around the bounds, so marches which start on them start clear of
+// any surface touching them.
+const BOUNDS_MARGIN: f64 = 1e-3;
+const NORMAL_OFFSET: f64 = 1e-5;
+
+pub enum Sdf {
+    Sphere(f64),
+    Box(Vector3<f64>),
+    Torus(f64, f64),
+    Capsule(Point3<f64>, Point3<f64>, f64),
+    Mandelbulb(f64, usize),
+    Translate(Box<Sdf>, Vector3<f64>),
+    SmoothUnion(Box<Sdf>, Box<Sdf>, f64),
+    SmoothSubtraction(Box<Sdf>, Box<Sdf>, f64),
+    Repeat(Box<Sdf>, Vector3<f64>),
+    Twist(Box<Sdf>, f64),
+}
+
+impl Sdf {
+    pub fn sphere(radius: f64) -> Self {
+        Sdf::Sphere(radius)
+    }
+
+    pub fn cuboid(half_size: Vector3<f64>) -> Self {
+        Sdf::Box(half_size)
+    }
+
+    // A ring lying flat in the xz plane.
+    pub fn torus(major_radius: f64, minor_radius: f64) -> Self {
+        Sdf::Torus(major_radius, minor_radius)
+    }
+
+    pub fn capsule(start: Point3<f64>, end: Point3<f64>, radius: f64) -> Self {
+        Sdf::Capsule(start, end, radius)
+    }
+
+    pub fn mandelbulb(power: f64, iterations: usize) -> Self {
+        Sdf::Mandelbulb(power, iterations)
+    }
+
+    pub fn translate(self, offset: Vector3<f64>) -> Self {
+        Sdf::Translate(Box::new(self), offset)
+    }
+
+    pub fn smooth_union(self, other: Sdf, smoothing: f64) -> Self {
+        Sdf::SmoothUnion(Box::new(self), Box::new(other), smoothing)
+    }
+
+    pub fn smooth_subtraction(self, other: Sdf, smoothing: f64) -> Self {
+        Sdf::SmoothSubtraction(Box::new(self), Box::new(other), smoothing)
+    }
+
+    // Repeats space in cells of the given size. Axes with a zero period are
+    // left alone.
+    pub fn repeat(self, period: Vector3<f64>) -> Self {
+        Sdf::Repeat(Box::new(self), period)
+    }
+
+    // Twists space around the y axis by `rate` radians per unit of height.
+    pub fn twist(self, rate: f64) -> Self {
+        Sdf::Twist(Box::new(self), rate)
+    }
+
+    pub fn distance(&self, p: &Point3<f64>) -> f64 {
+        match self {
+            Sdf::Sphere(radius) => p.coords.norm() - radius,
+            Sdf::Box(half_size) => {
+                let q = p.coords.abs() - half_size;
+                let outside = q.map(|v| v.max(0.0)).norm();
+                outside + q.max().min(0.0)
+            }
+            Sdf::Torus(major_radius, minor_radius) => {
+                let ring = (p.x * p.x + p.z * p.z).sqrt() - major_radius;
+                (ring * ring + p.y * p.y).sqrt() - minor_radius
+            }
+            Sdf::Capsule(start, end, radius) => {
+                let pa = p - start;
+                let ba = end - start;
+                let h = (pa.dot(&ba) / ba.dot(&ba)).clamp(0.0, 1.0);
+                (pa - ba * h).norm() - radius
+            }
+            Sdf::Mandelbulb(power, iterations) => mandelbulb(p, *power, *iterations),
+            Sdf::Translate(inner, offset) => inner.distance(&(p - offset)),
+            Sdf::SmoothUnion(a, b, k) => {
+                let (d1, d2) = (a.distance(p), b.distance(p));
+                let h = (0.5 + 0.5 * (d2 - d1) / k).clamp(0.0, 1.0);
+                d2 + (d1 - d2) * h - k * h * (1.0 - h)
+            }
+            Sdf::SmoothSubtraction(a, b, k) => {
+                let (d1, d2) = (a.distance(p), b.distance(p));
+                let h = (0.5 - 0.5 * (d1 + d2) / k).clamp(0.0, 1.0);
+                d1 + (-d2 - d1) * h + k * h * (1.0 - h)
+            }
+            Sdf::Repeat(inner, period) => {
+                let cell = |v: f64, size: f64| {
+                    if size == 0.0 {
+                        v
+                    } else {
+                        v - size * (v / size).round()
+                    }
+                };
+                inner.distance(&Point3::new(
+                    cell(p.x, period.x),
+                    cell(p.y, period.y),
+                    cell(p.z, period.z),
+                ))
+            }
+            Sdf::Twist(inner, rate) => {
+                let (s, c) = (rate * p.y).sin_cos();
+                inner.distance(&Point3::new(c * p.x - s * p.z, p.y, s * p.x + c * p.z))
+            }
+        }
+    }
+
+    // A box holding the whole surface, as its least and greatest corners.
+    // Repeated axes have no bounds.
+    pub fn bounds(&self) -> (Point3<f64>, Point3<f64>) {
+        let cube = |half: f64| (Point3::new(-half, -half, -half), Point3::new(half, half, half));
+        match self {
+            Sdf::Sphere(radius) => cube(*radius),
+            Sdf::Box(half_size) => (Point3::from(-half_size), Point3::from(*half_size)),
+            Sdf::Torus(major_radius, minor_radius) => {
+                let across = major_radius + minor_radius;
+                (
+                    Point3::new(-across, -minor_radius, -across),
+                    Point3::new(across, *minor_radius, across),
+                )
+            }
+            Sdf::Capsule(start, end, radius) => {
+                let pad = Vector3::new(*radius, *radius, *radius);
+                (
+                    Point3::from(start.coords.zip_map(&end.coords, f64::min) - pad),
+                    Point3::from(start.coords.zip_map(&end.coords, f64::max) + pad),
+                )
+            }
+            // Beyond this the distance is measured to a sphere within it.
+            Sdf::Mandelbulb(..) => cube(1.5),
+            Sdf::Translate(inner, offset) => {
+                let (min, max) = inner.bounds();
+                (min + offset, max + offset)
+            }
+            // Blending reaches at most a quarter of the smoothing past either
+            // shape, while subtracting never reaches past the first.
+            Sdf::SmoothUnion(a, b, k) => {
+                let ((a_min, a_max), (b_min, b_max)) = (a.bounds(), b.bounds());
+                let pad = Vector3::repeat(k.abs() * 0.25);
+                (
+                    Point3::from(a_min.coords.zip_map(&b_min.coords, f64::min) - pad),
+                    Point3::from(a_max.coords.zip_map(&b_max.coords, f64::max) + pad),
+                )
+            }
+            Sdf::SmoothSubtraction(a, _, _) => a.bounds(),
+            Sdf::Repeat(inner, period) => {
+                let (mut min, mut max) = inner.bounds();
+                for axis in 0..3 {
+                    if period[axis] != 0.0 {
+                        min[axis] = f64::NEG_INFINITY;
+                        max[axis] = f64::INFINITY;
+                    }
+                }
+                (min, max)
+            }
+            // Twisting sweeps the shape round the y axis.
+            Sdf::Twist(inner, _) => {
+                let (min, max) = inner.bounds();
+                let x = min.x.abs().max(max.x.abs());
+                let z = min.z.abs().max(max.z.abs());
+                let radius = (x * x + z * z).sqrt();
+                (Point3::new(-radius, min.y, -radius), Point3::new(radius, max.y, radius))
+            }
+        }
+    }
+
+    // Central differences of the distance field.
+    pub fn normal(&self, p: &Point3<f64>) -> Vector3<f64> {
+        let offset = |x, y, z| self.distance(&(p + Vector3::new(x, y, z)));
+        let h = NORMAL_OFFSET;
+        Vector3::new(
+            offset(h, 0.0, 0.0) - offset(-h, 0.0, 0.0),
+            offset(0.0, h, 0.0) - offset(0.0, -h, 0.0),
+            offset(0.0, 0.0, h) - offset(0.0, 0.0, -h),
+        )
+        .normalize()
+    }
+}
+
+// Distance estimate from the running derivative of the bulb's power series.
+// The estimate is only trustworthy close to the bulb, so distant points are
+// measured to a sphere which bounds it instead.
+fn mandelbulb(p: &Point3<f64>, power: f64, iterations: usize) -> f64 {
+    let bounds = p.coords.norm();
+    if bounds > 1.5 {
+        return bounds - 1.2;
+    }
+
+    let mut z = p.coords;
+    let mut dr = 1.0;
+    let mut r = z.norm();
+    for _ in 0..iterations {
+        r = z.norm();
+        if r > 2.0 || r == 0.0 {
+            break;
+        }
+
+        let theta = (z.z / r).acos() * power;
+        let phi = z.y.atan2(z.x) * power;
+        dr = r.powf(power - 1.0) * power * dr + 1.0;
+        z = Vector3::new(
+            theta.sin() * phi.cos(),
+            phi.sin() * theta.sin(),
+            theta.cos(),
+        ) * r.powf(power)
+            + p.coords;
+    }
+
+    if r == 0.0 {
+        return 0.0;
+    }
+    0.5 * r.ln() * r / dr
+}
+
+pub struct SdfObject {
+    sdf: Sdf,
+    bounds: (Point3<f64>, Point3<f64>),
+    step_scale: f64,
+    material: Material,
+}
+
+impl SdfObject {
+    pub fn new(sdf: Sdf, material: Material) -> Self {
+        let (min, max) = sdf.bounds();
+        let margin = Vector3::repeat(BOUNDS_MARGIN);
+        SdfObject {
+            sdf,
+            bounds: (min - margin, max + margin),
+            step_scale: 1.0,
+            material,
+        }
+    }
+
+    // Fields which only estimate their distance, such as twisted space or
+    // fractals, need shorter steps to avoid overshooting the surface.
+    pub fn with_step_scale(mut self, step_scale: f64) -> Self {
+        self.step_scale = step_scale;
+        self
+    }
+
+    // The stretch of the ray inside the bounds.
+    fn bounded(&self, ray: &Ray) -> Option<(f64, f64)> {
+        let (min, max) = &self.bounds;
+        let mut near = f64::NEG_INFINITY;
+        let mut far = f64::INFINITY;
+        for axis in 0..3 {
+            let inverse = 1.0 / ray.direction[axis];
+            let mut t0 = (min[axis] - ray.origin[axis]) * inverse;
+            let mut t1 = (max[axis] - ray.origin[axis]) * inverse;
+            if t0 > t1 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            near = near.max(t0);
+            far = far.min(t1);
+        }
+
+        if near > far || far < SURFACE_DISTANCE {
+            None
+        } else {
+            Some((near, far))
+        }
+    }
+}
+
+impl Shape for SdfObject {
+    fn intersection_distance(&self, ray: &Ray) -> f64 {
+        let (near, far) = match self.bounded(ray) {
+            Some(stretch) => stretch,
+            None => return f64::INFINITY,
+        };
+
+        // Rays leaving the surface start right on it, so step clear of it and
+        // march on whichever side of the surface the ray begins.
+        let mut t = near.max(SURFACE_DISTANCE);
+        let mut start = self.sdf.distance(&(ray.origin + ray.direction * t));
+        for _ in 0..MAX_STEPS {
+            if start.abs() >= SURFACE_DISTANCE {
+                break;
+            }
+            t += SURFACE_DISTANCE;
+            start = self.sdf.distance(&(ray.origin + ray.direction * t));
+        }
+        let side = start.signum();
+
+        for _ in 0..MAX_STEPS {
+            let distance = side * self.sdf.distance(&(ray.origin + ray.direction * t));
+            if distance < SURFACE_DISTANCE {
+                return t;
+            }
+
+            t += distance * self.step_scale;
+            if t > far {
+                break;
+            }
+        }
+
+        f64::INFINITY
+    }
+
+    fn surface(&self, ray: &Ray, distance: f64) -> Surface {
+        let normal = self.sdf.normal(&(ray.origin + ray.direction * distance));
+        let u = 0.5 + normal.z.atan2(normal.x) / (2.0 * f64::consts::PI);
+        let v = 0.5 - normal.y.asin() / f64::consts::PI;
+        Surface {
+            normal,
+            uv: Point2::new(u, v),
+            front_face: normal.dot(&ray.direction) < 0.0,
+        }
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn object(sdf: Sdf) -> SdfObject {
+        let material = Material::new(
+            Vector3::new(1.0, 1.0, 1.0),
+            1.0,
+            0.0,
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.04, 0.04, 0.04),
+            0.0,
+            0.0,
+        );
+        SdfObject::new(sdf, material)
+    }
+
+    fn ray(origin: Point3<f64>, direction: Vector3<f64>) -> Ray {
+        Ray {
+            origin,
+            direction,
+            time: 0.0,
+        }
+    }
+
+    #[test]
+    fn sphere_tracing_finds_sphere_surface() {
+        let object = object(Sdf::sphere(1.0).translate(Vector3::new(0.0, 0.0, -5.0)));
+        let ray = ray(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0));
+
+        let distance = object.intersection_distance(&ray);
+        assert!((distance - 4.0).abs() < SURFACE_DISTANCE);
+
+        let normal = object.surface(&ray, distance).normal;
+        assert!((normal - Vector3::new(0.0, 0.0, 1.0)).norm() < 1e-3);
+    }
+
+    #[test]
+    fn distant_objects_are_found_and_rays_past_the_bounds_miss() {
+        let object = object(Sdf::sphere(1.0).translate(Vector3::new(0.0, 0.0, -500.0)));
+        let towards = ray(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0));
+        assert!((object.intersection_distance(&towards) - 499.0).abs() < SURFACE_DISTANCE);
+
+        let beside = ray(Point3::new(2.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0));
+        assert_eq!(object.intersection_distance(&beside), f64::INFINITY);
+    }
+
+    #[test]
+    fn smooth_union_bridges_the_gap_between_shapes() {
+        let apart = |smoothing| {
+            object(
+                Sdf::sphere(1.0)
+                    .translate(Vector3::new(-1.5, 0.0, 0.0))
+                    .smooth_union(Sdf::sphere(1.0).translate(Vector3::new(1.5, 0.0, 0.0)), smoothing),
+            )
+        };
+        let down = ray(Point3::new(0.0, 5.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+
+        // Midway between the spheres both are 1.75 away from the surface.
+        let height = (1.75f64 * 1.75 - 1.5 * 1.5).sqrt();
+        assert!((apart(3.0).intersection_distance(&down) - (5.0 - height)).abs() < 1e-3);
+        assert_eq!(apart(0.1).intersection_distance(&down), f64::INFINITY);
+    }
+
+    #[test]
+    fn smooth_subtraction_carves_into_the_first_shape() {
+        let object = object(
+            Sdf::sphere(2.0).smooth_subtraction(Sdf::sphere(1.0).translate(Vector3::new(0.0, 2.0, 0.0)), 0.1),
+        );
+        let down = ray(Point3::new(0.0, 5.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+
+        let distance = object.intersection_distance(&down);
+        assert!((distance - 4.0).abs() < 1e-3);
+        let normal = object.surface(&down, distance).normal;
+        assert!((normal - Vector3::new(0.0, 1.0, 0.0)).norm() < 1e-3);
+    }
+
+    #[test]
+    fn repetition_fills_every_cell() {
+        let object = object(Sdf::sphere(0.5).repeat(Vector3::new(2.0, 0.0, 0.0)));
+        let down = |x| ray(Point3::new(x, 0.0, 5.0), Vector3::new(0.0, 0.0, -1.0));
+
+        assert!((object.intersection_distance(&down(0.0)) - 4.5).abs() < 1e-3);
+        assert!((object.intersection_distance(&down(-40.0)) - 4.5).abs() < 1e-3);
+        assert_eq!(object.intersection_distance(&down(1.0)), f64::INFINITY);
+
+        // Along the repeated axis the first cell ahead is found.
+        let along = ray(Point3::new(-99.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
+        assert!((object.intersection_distance(&along) - 0.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn twist_turns_the_shape_with_height() {
+        // A quarter turn by the top of the ray turns the slab side on.
+        let object = object(Sdf::cuboid(Vector3::new(1.0, 2.0, 0.2)).twist(f64::consts::FRAC_PI_2))
+            .with_step_scale(0.5);
+        let across = |x, y| ray(Point3::new(x, y, 5.0), Vector3::new(0.0, 0.0, -1.0));
+
+        assert!((object.intersection_distance(&across(0.0, 0.0)) - 4.8).abs() < 1e-3);
+        assert!((object.intersection_distance(&across(0.0, 1.0)) - 4.0).abs() < 1e-3);
+        assert!((object.intersection_distance(&across(0.5, 0.0)) - 4.8).abs() < 1e-3);
+        assert_eq!(object.intersection_distance(&across(0.5, 1.0)), f64::INFINITY);
+    }
+}
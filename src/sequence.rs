@@ -0,0 +1,75 @@
+use crate::scene::Scene;
+use crate::tracer::Tracer;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+
+// Renders frames of an animation one after another, each to its own
+// numbered image.
+pub struct Sequence {
+    frames: Range<usize>,
+    frame_rate: f64,
+    samples: usize,
+    bounces: u32,
+    width: usize,
+    height: usize,
+}
+
+impl Sequence {
+    pub fn new(frames: Range<usize>, frame_rate: f64) -> Self {
+        Sequence {
+            frames,
+            frame_rate,
+            samples: 64,
+            bounces: 10,
+            width: 400,
+            height: 400,
+        }
+    }
+
+    pub fn with_size(mut self, width: usize, height: usize) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    // Samples taken in each pixel of every frame.
+    pub fn with_samples(mut self, samples: usize) -> Self {
+        self.samples = samples;
+        self
+    }
+
+    pub fn with_bounces(mut self, bounces: u32) -> Self {
+        self.bounces = bounces;
+        self
+    }
+
+    // Builds the scene at the time of each frame and writes it out to
+    // frame_0001.ppm and so on in `directory`.
+    pub fn render<F>(&self, scene_at: F, directory: &Path) -> Result<(), String>
+    where
+        F: Fn(f64) -> Result<Scene, String>,
+    {
+        for frame in self.frames.clone() {
+            let scene = scene_at(frame as f64 / self.frame_rate)?;
+            let mut tracer = Tracer::new(scene, self.bounces, 2.2, self.width, self.height);
+            let mut pixels = vec![0u8; self.width * self.height * 4];
+            tracer.render(self.samples, &mut pixels);
+
+            let path = directory.join(format!("frame_{:04}.ppm", frame));
+            fs::write(&path, ppm(self.width, self.height, &pixels))
+                .map_err(|error| format!("{}: {}", path.display(), error))?;
+        }
+        Ok(())
+    }
+}
+
+// A binary PPM image, which needs nothing more than a header in front of the
+// RGB bytes.
+fn ppm(width: usize, height: usize, pixels: &[u8]) -> Vec<u8> {
+    let mut image = format!("P6\n{} {}\n255\n", width, height).into_bytes();
+    for rgba in pixels.chunks(4) {
+        image.extend_from_slice(&rgba[..3]);
+    }
+    image
+}
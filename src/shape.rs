@@ -0,0 +1,162 @@
+use crate::material::Material;
+use crate::ray::Ray;
+use nalgebra::{Point2, Point3, Vector3};
+
+pub const BIAS: f64 = 1e-6;
+
+pub struct Surface {
+    pub normal: Vector3<f64>,
+    pub uv: Point2<f64>,
+    pub front_face: bool,
+}
+
+pub struct SurfaceSample {
+    pub point: Point3<f64>,
+    pub normal: Vector3<f64>,
+}
+
+// A stretch of a ray which lies inside a solid, including any part of it
+// behind the ray origin.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Span {
+    pub enter: f64,
+    pub exit: f64,
+}
+
+pub trait Shape {
+    fn intersection_distance(&self, ray: &Ray) -> f64;
+    fn surface(&self, ray: &Ray, distance: f64) -> Surface;
+    fn material(&self) -> &Material;
+
+    // Direction along the surface which anisotropic materials line their
+    // highlights up against. By default it runs in circles around the
+    // vertical axis.
+    fn tangent(&self, _point: &Point3<f64>, normal: &Vector3<f64>) -> Vector3<f64> {
+        let around = Vector3::new(0.0, 1.0, 0.0).cross(normal);
+        if around.norm() > 1e-6 {
+            around.normalize()
+        } else {
+            orthonormal_basis(normal).0
+        }
+    }
+
+    // How far the shape has moved from where it is described by `time`, for
+    // shapes which move while the shutter is open. Surfaces are sampled as
+    // they stand at time zero and then moved along by this.
+    fn displacement(&self, _time: f64) -> Vector3<f64> {
+        Vector3::zeros()
+    }
+
+    // Shapes which can't be sampled report no area and are never used for
+    // direct light sampling.
+    fn area(&self) -> f64 {
+        0.0
+    }
+
+    fn sample_surface(&self) -> Option<SurfaceSample> {
+        None
+    }
+
+    // Shapes without an inside report no spans and take no part in
+    // constructive solid geometry.
+    fn spans(&self, _ray: &Ray) -> Vec<Span> {
+        vec![]
+    }
+}
+
+// Flat shapes have no inside, so their normal always faces the incoming ray.
+// The second value records whether the ray arrived at the front face.
+pub fn facing(normal: Vector3<f64>, ray: &Ray) -> (Vector3<f64>, bool) {
+    if normal.dot(&ray.direction) > 0.0 {
+        (-normal, false)
+    } else {
+        (normal, true)
+    }
+}
+
+pub fn orthonormal_basis(axis: &Vector3<f64>) -> (Vector3<f64>, Vector3<f64>) {
+    let helper = if axis.x.abs() > 0.9 {
+        Vector3::new(0.0, 1.0, 0.0)
+    } else {
+        Vector3::new(1.0, 0.0, 0.0)
+    };
+    let tangent = axis.cross(&helper).normalize();
+    let bitangent = axis.cross(&tangent);
+    (tangent, bitangent)
+}
+
+pub struct Frame {
+    origin: Point3<f64>,
+    tangent: Vector3<f64>,
+    axis: Vector3<f64>,
+    bitangent: Vector3<f64>,
+}
+
+impl Frame {
+    pub fn new(origin: Point3<f64>, axis: Vector3<f64>) -> Self {
+        let axis = axis.normalize();
+        let (tangent, bitangent) = orthonormal_basis(&axis);
+        Frame {
+            origin,
+            tangent,
+            axis,
+            bitangent,
+        }
+    }
+
+    pub fn point_to_local(&self, point: &Point3<f64>) -> Point3<f64> {
+        let offset = point - self.origin;
+        Point3::from(self.vector_to_local(&offset))
+    }
+
+    pub fn vector_to_local(&self, vector: &Vector3<f64>) -> Vector3<f64> {
+        Vector3::new(
+            vector.dot(&self.tangent),
+            vector.dot(&self.axis),
+            vector.dot(&self.bitangent),
+        )
+    }
+
+    pub fn vector_to_world(&self, vector: &Vector3<f64>) -> Vector3<f64> {
+        self.tangent * vector.x + self.axis * vector.y + self.bitangent * vector.z
+    }
+
+    pub fn ray_to_local(&self, ray: &Ray) -> Ray {
+        Ray {
+            origin: self.point_to_local(&ray.origin),
+            direction: self.vector_to_local(&ray.direction),
+            time: ray.time,
+        }
+    }
+}
+
+// Any line crosses a convex solid at most once, so its span runs between the
+// nearest and furthest boundary crossings.
+pub fn convex_span<I: Iterator<Item = f64>>(distances: I) -> Vec<Span> {
+    let (enter, exit) = distances.fold((f64::INFINITY, f64::NEG_INFINITY), |(enter, exit), t| {
+        (enter.min(t), exit.max(t))
+    });
+    if enter < exit {
+        vec![Span { enter, exit }]
+    } else {
+        vec![]
+    }
+}
+
+pub fn quadratic_roots(a: f64, b: f64, c: f64) -> Vec<f64> {
+    if a.abs() < f64::EPSILON {
+        return if b.abs() < f64::EPSILON {
+            vec![]
+        } else {
+            vec![-c / b]
+        };
+    }
+
+    let det = b * b - 4.0 * a * c;
+    if det < 0.0 {
+        return vec![];
+    }
+
+    let det_root = det.sqrt();
+    vec![(-b - det_root) / (2.0 * a), (-b + det_root) / (2.0 * a)]
+}
@@ -0,0 +1,216 @@
+use nalgebra::{Matrix3, Vector3};
+use std::f64;
+
+pub const MIN_WAVELENGTH: f64 = 380.0;
+pub const MAX_WAVELENGTH: f64 = 720.0;
+const RANGE: f64 = MAX_WAVELENGTH - MIN_WAVELENGTH;
+
+// Smits' basis spectra for turning RGB into reflectance spectra, in ten even
+// bins from 380nm to 720nm.
+const WHITE: [f64; 10] = [1.0000, 1.0000, 0.9999, 0.9993, 0.9992, 0.9998, 1.0000, 1.0000, 1.0000, 1.0000];
+const CYAN: [f64; 10] = [0.9710, 0.9426, 1.0007, 1.0007, 1.0007, 1.0007, 0.1564, 0.0000, 0.0000, 0.0000];
+const MAGENTA: [f64; 10] = [1.0000, 1.0000, 0.9685, 0.2229, 0.0000, 0.0458, 0.8369, 1.0000, 1.0000, 0.9959];
+const YELLOW: [f64; 10] = [0.0001, 0.0000, 0.1088, 0.6651, 1.0000, 1.0000, 0.9996, 0.9586, 0.9685, 0.9840];
+const RED: [f64; 10] = [0.1012, 0.0515, 0.0000, 0.0000, 0.0000, 0.0000, 0.8325, 1.0149, 1.0149, 1.0149];
+const GREEN: [f64; 10] = [0.0000, 0.0000, 0.0273, 0.7937, 1.0000, 0.9418, 0.1719, 0.0000, 0.0000, 0.0025];
+const BLUE: [f64; 10] = [1.0000, 1.0000, 0.8916, 0.3323, 0.0000, 0.0000, 0.0003, 0.0369, 0.0483, 0.0496];
+
+// Refractive index as a function of wavelength.
+#[derive(Copy, Clone)]
+pub enum Dispersion {
+    // A + B / λ², with λ in micrometres.
+    Cauchy(f64, f64),
+    // Three term Sellmeier equation, with C in square micrometres.
+    Sellmeier([f64; 3], [f64; 3]),
+}
+
+impl Dispersion {
+    pub fn cauchy(a: f64, b: f64) -> Self {
+        Dispersion::Cauchy(a, b)
+    }
+
+    pub fn sellmeier(b: [f64; 3], c: [f64; 3]) -> Self {
+        Dispersion::Sellmeier(b, c)
+    }
+
+    // Common borosilicate crown glass.
+    pub fn bk7() -> Self {
+        Dispersion::sellmeier(
+            [1.039_612_12, 0.231_792_344, 1.010_469_45],
+            [0.006_000_698_67, 0.020_017_914_4, 103.560_653],
+        )
+    }
+
+    // Dense flint glass, which splits light far more strongly.
+    pub fn sf11() -> Self {
+        Dispersion::sellmeier(
+            [1.737_596_95, 0.313_747_346, 1.898_781_01],
+            [0.013_188_707, 0.062_306_814_2, 155.236_29],
+        )
+    }
+
+    // Index at a wavelength in nanometres.
+    pub fn index(&self, wavelength: f64) -> f64 {
+        let micrometres = wavelength / 1000.0;
+        let square = micrometres * micrometres;
+        match self {
+            Dispersion::Cauchy(a, b) => a + b / square,
+            Dispersion::Sellmeier(b, c) => {
+                let sum: f64 = (0..3).map(|i| b[i] * square / (square - c[i])).sum();
+                (1.0 + sum).sqrt()
+            }
+        }
+    }
+}
+
+// Three wavelengths carried by a path in place of red, green and blue. The
+// first, hero, wavelength is picked at random and the others are spaced
+// evenly from it across the visible range.
+pub struct Wavelengths {
+    values: Vector3<f64>,
+    single: bool,
+}
+
+impl Wavelengths {
+    pub fn sample() -> Self {
+        let hero = rand::random::<f64>() * RANGE;
+        let values = Vector3::new(0.0, 1.0, 2.0)
+            .map(|i| MIN_WAVELENGTH + (hero + i * RANGE / 3.0) % RANGE);
+        Wavelengths {
+            values,
+            single: false,
+        }
+    }
+
+    pub fn hero(&self) -> f64 {
+        self.values.x
+    }
+
+    // Wavelength dependent events, such as dispersion, send each wavelength a
+    // different way, so only the hero wavelength carries on.
+    pub fn keep_hero(&mut self, signal: &mut Vector3<f64>) {
+        if !self.single {
+            *signal = Vector3::new(signal.x * 3.0, 0.0, 0.0);
+            self.single = true;
+        }
+    }
+
+    // Values of the smooth spectrum matching an RGB colour at each wavelength.
+    pub fn upsample(&self, rgb: &Vector3<f64>) -> Vector3<f64> {
+        self.values.map(|wavelength| upsample(rgb, wavelength))
+    }
+}
+
+fn upsample(rgb: &Vector3<f64>, wavelength: f64) -> f64 {
+    let (r, g, b) = (rgb.x, rgb.y, rgb.z);
+    let at = |basis: &[f64; 10]| bin(basis, wavelength);
+    if r <= g && r <= b {
+        r * at(&WHITE)
+            + if g <= b {
+                (g - r) * at(&CYAN) + (b - g) * at(&BLUE)
+            } else {
+                (b - r) * at(&CYAN) + (g - b) * at(&GREEN)
+            }
+    } else if g <= r && g <= b {
+        g * at(&WHITE)
+            + if r <= b {
+                (r - g) * at(&MAGENTA) + (b - r) * at(&BLUE)
+            } else {
+                (b - g) * at(&MAGENTA) + (r - b) * at(&RED)
+            }
+    } else {
+        b * at(&WHITE)
+            + if r <= g {
+                (r - b) * at(&YELLOW) + (g - r) * at(&GREEN)
+            } else {
+                (g - b) * at(&YELLOW) + (r - g) * at(&RED)
+            }
+    }
+}
+
+// Interpolates linearly between bin centres.
+fn bin(basis: &[f64; 10], wavelength: f64) -> f64 {
+    let position = ((wavelength - MIN_WAVELENGTH) / RANGE * 10.0 - 0.5).clamp(0.0, 9.0);
+    let index = (position as usize).min(8);
+    let t = position - index as f64;
+    basis[index] * (1.0 - t) + basis[index + 1] * t
+}
+
+// Converts spectral samples back into linear sRGB, scaled so that a flat
+// spectrum of one comes out white.
+pub struct SpectralFilm {
+    white: Vector3<f64>,
+}
+
+impl SpectralFilm {
+    pub fn new() -> Self {
+        let steps = 340;
+        let flat = (0..steps).fold(Vector3::zeros(), |sum, i| {
+            let wavelength = MIN_WAVELENGTH + (i as f64 + 0.5) * RANGE / steps as f64;
+            sum + matching(wavelength) * (RANGE / steps as f64)
+        });
+        SpectralFilm {
+            white: xyz_to_srgb() * flat,
+        }
+    }
+
+    pub fn to_rgb(&self, wavelengths: &Wavelengths, radiance: &Vector3<f64>) -> Vector3<f64> {
+        let xyz = (0..3).fold(Vector3::zeros(), |sum, i| {
+            sum + matching(wavelengths.values[i]) * (radiance[i] * RANGE / 3.0)
+        });
+        (xyz_to_srgb() * xyz).component_div(&self.white)
+    }
+}
+
+impl Default for SpectralFilm {
+    fn default() -> Self {
+        SpectralFilm::new()
+    }
+}
+
+// CIE 1931 colour matching functions, using the multi-lobe Gaussian fit from
+// Wyman, Sloan and Shirley.
+fn matching(wavelength: f64) -> Vector3<f64> {
+    let lobe = |mean: f64, below: f64, above: f64| {
+        let t = (wavelength - mean) / if wavelength < mean { below } else { above };
+        (-0.5 * t * t).exp()
+    };
+    Vector3::new(
+        1.056 * lobe(599.8, 37.9, 31.0) + 0.362 * lobe(442.0, 16.0, 26.7)
+            - 0.065 * lobe(501.1, 20.4, 26.2),
+        0.821 * lobe(568.8, 46.9, 40.5) + 0.286 * lobe(530.9, 16.3, 31.1),
+        1.217 * lobe(437.0, 11.8, 36.0) + 0.681 * lobe(459.0, 26.0, 13.8),
+    )
+}
+
+fn xyz_to_srgb() -> Matrix3<f64> {
+    Matrix3::new(
+        3.2406, -1.5372, -0.4986, //
+        -0.9689, 1.8758, 0.0415, //
+        0.0557, -0.2040, 1.0570,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn grey_survives_the_round_trip() {
+        let film = SpectralFilm::new();
+        let grey = Vector3::new(0.5, 0.5, 0.5);
+        let samples = 20000;
+        let total = (0..samples).fold(Vector3::zeros(), |sum, _| {
+            let wavelengths = Wavelengths::sample();
+            sum + film.to_rgb(&wavelengths, &wavelengths.upsample(&grey))
+        });
+        assert!((total / samples as f64 - grey).amax() < 0.02);
+    }
+
+    #[test]
+    fn glass_bends_blue_more_than_red() {
+        let glass = Dispersion::bk7();
+        assert!((glass.index(587.6) - 1.5168).abs() < 1e-3);
+        assert!(glass.index(450.0) > glass.index(650.0));
+    }
+}
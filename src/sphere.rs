@@ -1,6 +1,7 @@
 use crate::material::Material;
 use crate::ray::Ray;
-use nalgebra::Point3;
+use crate::shape::{Shape, Span, Surface, SurfaceSample};
+use nalgebra::{Point2, Point3, Vector3};
 use std::f64;
 
 pub struct Sphere {
@@ -17,16 +18,10 @@ impl Sphere {
             material,
         }
     }
+}
 
-    pub fn center(&self) -> Point3<f64> {
-        self.center
-    }
-
-    pub fn material(&self) -> &Material {
-        &self.material
-    }
-
-    pub fn intersection_distance(&self, ray: &Ray) -> f64 {
+impl Shape for Sphere {
+    fn intersection_distance(&self, ray: &Ray) -> f64 {
         let bias = 1e-6;
         let op = self.center - ray.origin;
         let b = op.dot(&ray.direction);
@@ -48,4 +43,50 @@ impl Sphere {
 
         f64::INFINITY
     }
+
+    fn surface(&self, ray: &Ray, distance: f64) -> Surface {
+        let point = ray.origin + (ray.direction * distance);
+        let normal = (point - self.center).normalize();
+        let u = 0.5 + normal.z.atan2(normal.x) / (2.0 * f64::consts::PI);
+        let v = 0.5 - normal.y.asin() / f64::consts::PI;
+        Surface {
+            normal,
+            uv: Point2::new(u, v),
+            front_face: normal.dot(&ray.direction) < 0.0,
+        }
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn area(&self) -> f64 {
+        4.0 * f64::consts::PI * self.radius * self.radius
+    }
+
+    fn sample_surface(&self) -> Option<SurfaceSample> {
+        let z = 1.0 - 2.0 * rand::random::<f64>();
+        let r = (1.0 - z * z).max(0.0).sqrt();
+        let phi = 2.0 * f64::consts::PI * rand::random::<f64>();
+        let normal = Vector3::new(r * phi.cos(), r * phi.sin(), z);
+        Some(SurfaceSample {
+            point: self.center + normal * self.radius,
+            normal,
+        })
+    }
+
+    fn spans(&self, ray: &Ray) -> Vec<Span> {
+        let op = self.center - ray.origin;
+        let b = op.dot(&ray.direction);
+        let det = b * b - op.dot(&op) + self.radius * self.radius;
+        if det < 0f64 {
+            return vec![];
+        }
+
+        let det_root = det.sqrt();
+        vec![Span {
+            enter: b - det_root,
+            exit: b + det_root,
+        }]
+    }
 }
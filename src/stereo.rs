@@ -0,0 +1,121 @@
+use crate::camera::{image_point, Camera, View};
+use crate::panorama::equirectangular_direction;
+use crate::ray::Ray;
+use nalgebra::{Point3, Vector3};
+
+// Where the two eyes' images go in the frame.
+#[derive(Copy, Clone)]
+pub enum Layout {
+    SideBySide,
+    OverUnder,
+}
+
+// Renders a left and a right eye camera into the two halves of one image.
+pub struct Stereo {
+    left: Box<dyn Camera>,
+    right: Box<dyn Camera>,
+    layout: Layout,
+}
+
+impl Stereo {
+    pub fn new(left: Box<dyn Camera>, right: Box<dyn Camera>, layout: Layout) -> Self {
+        Stereo { left, right, layout }
+    }
+
+    // An equirectangular panorama for each eye, with every ray leaving from
+    // the edge of a circle as wide as the distance between the eyes.
+    pub fn omnidirectional(view: View, interocular: f64, layout: Layout) -> Self {
+        Stereo::new(
+            Box::new(OmniStereo::new(view, -0.5 * interocular)),
+            Box::new(OmniStereo::new(view, 0.5 * interocular)),
+            layout,
+        )
+    }
+}
+
+impl Camera for Stereo {
+    fn focus_on(&mut self, point: &Point3<f64>) -> Option<f64> {
+        let left = self.left.focus_on(point);
+        self.right.focus_on(point).or(left)
+    }
+
+    fn ray(&self, x: usize, y: usize, width: usize, height: usize) -> Option<Ray> {
+        match self.layout {
+            Layout::SideBySide => {
+                let half = width / 2;
+                if x < half {
+                    self.left.ray(x, y, half, height)
+                } else {
+                    self.right.ray(x - half, y, width - half, height)
+                }
+            }
+            Layout::OverUnder => {
+                let half = height / 2;
+                if y < half {
+                    self.left.ray(x, y, width, half)
+                } else {
+                    self.right.ray(x, y - half, width, height - half)
+                }
+            }
+        }
+    }
+}
+
+// One eye of an omnidirectional stereo panorama. Each ray starts `offset` to
+// the right of the centre, perpendicular to its own direction, so that
+// every column of the image sees the scene with the right parallax. The
+// offset shrinks towards the poles, where the eyes can't be told apart.
+pub struct OmniStereo {
+    view: View,
+    offset: f64,
+}
+
+impl OmniStereo {
+    pub fn new(view: View, offset: f64) -> Self {
+        OmniStereo { view, offset }
+    }
+}
+
+impl Camera for OmniStereo {
+    fn ray(&self, x: usize, y: usize, width: usize, height: usize) -> Option<Ray> {
+        let (u, v) = image_point(x, y, width, height);
+        let direction = equirectangular_direction(u, v);
+        let horizontal = Vector3::new(direction.x, 0.0, direction.z);
+        let right = Vector3::new(-horizontal.z, 0.0, horizontal.x);
+
+        Some(Ray {
+            origin: self.view.position + self.view.to_world(&(right * self.offset)),
+            direction: self.view.to_world(&direction),
+            time: 0.0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::panorama::Equirectangular;
+    use nalgebra::Point3;
+
+    #[test]
+    fn eyes_sit_either_side_of_the_view() {
+        let view = View::look_at(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(0.0, 0.0, -1.0),
+            Vector3::new(0.0, 1.0, 0.0),
+        );
+        let pair = Stereo::new(
+            Box::new(Equirectangular::new(view.eye(-0.5, 10.0))),
+            Box::new(Equirectangular::new(view.eye(0.5, 10.0))),
+            Layout::SideBySide,
+        );
+        assert_eq!(pair.ray(10, 10, 100, 50).unwrap().origin, Point3::new(-0.5, 0.0, 0.0));
+        assert_eq!(pair.ray(60, 10, 100, 50).unwrap().origin, Point3::new(0.5, 0.0, 0.0));
+
+        // Looking straight ahead from the middle of an omnidirectional
+        // panorama, the right eye is to the right.
+        let ods = Stereo::omnidirectional(view, 1.0, Layout::OverUnder);
+        let right_eye = ods.ray(50, 37, 101, 50).unwrap();
+        assert!((right_eye.origin - Point3::new(0.5, 0.0, 0.0)).norm() < 0.05);
+    }
+}
@@ -0,0 +1,48 @@
+use crate::volume::Noise;
+use nalgebra::{Point2, Point3};
+
+// A single value which varies over a surface, looked up by texture
+// coordinates.
+#[derive(Clone)]
+pub enum Texture {
+    Constant(f64),
+    // Blends from `start` at v = 0 to `end` at v = 1.
+    Gradient(f64, f64),
+    // Fractal noise scaled to run between a low and a high value.
+    Noise(Noise, f64, f64),
+    // Squares alternating between a low and a high value, `frequency` to
+    // each unit of u and v.
+    Checker(f64, f64, f64),
+}
+
+impl Texture {
+    pub fn constant(value: f64) -> Self {
+        Texture::Constant(value)
+    }
+
+    pub fn gradient(start: f64, end: f64) -> Self {
+        Texture::Gradient(start, end)
+    }
+
+    pub fn noise(frequency: f64, octaves: usize, low: f64, high: f64) -> Self {
+        Texture::Noise(Noise::new(frequency, octaves, 0.0), low, high)
+    }
+
+    pub fn checker(frequency: f64, low: f64, high: f64) -> Self {
+        Texture::Checker(frequency, low, high)
+    }
+
+    pub fn value(&self, uv: &Point2<f64>) -> f64 {
+        match self {
+            Texture::Constant(value) => *value,
+            Texture::Gradient(start, end) => start + (end - start) * uv.y.clamp(0.0, 1.0),
+            Texture::Noise(noise, low, high) => {
+                low + (high - low) * noise.density(&Point3::new(uv.x, uv.y, 0.0))
+            }
+            Texture::Checker(frequency, low, high) => {
+                let square = (uv.x * frequency).floor() + (uv.y * frequency).floor();
+                if square.rem_euclid(2.0) < 1.0 { *low } else { *high }
+            }
+        }
+    }
+}
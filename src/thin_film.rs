@@ -0,0 +1,86 @@
+use crate::texture::Texture;
+use nalgebra::{Point2, Vector3};
+use std::f64;
+
+// Wavelengths in nanometres standing in for the red, green and blue channels.
+const CHANNEL_WAVELENGTHS: [f64; 3] = [650.0, 532.0, 450.0];
+
+// A transparent coating, such as soap, oil or an oxide layer, thin enough that
+// light reflected from its top and bottom interferes.
+#[derive(Clone)]
+pub struct ThinFilm {
+    thickness: Texture,
+    index: f64,
+}
+
+impl ThinFilm {
+    // `thickness` is in nanometres.
+    pub fn new(thickness: Texture, index: f64) -> Self {
+        ThinFilm { thickness, index }
+    }
+
+    // Airy reflectance of the film over a substrate, averaged over both
+    // polarisations. The substrate's index in each channel is recovered from
+    // its reflectance at normal incidence.
+    pub fn reflectance(
+        &self,
+        cos_incident: f64,
+        substrate_reflectance: &Vector3<f64>,
+        uv: &Point2<f64>,
+    ) -> Vector3<f64> {
+        let thickness = self.thickness.value(uv);
+        let cos_film = refracted_cos(cos_incident, 1.0, self.index);
+
+        Vector3::from_fn(|channel, _| {
+            let root = substrate_reflectance[channel].clamp(0.0, 0.99).sqrt();
+            let substrate = (1.0 + root) / (1.0 - root);
+            let cos_substrate = refracted_cos(cos_incident, 1.0, substrate);
+            let phase = 4.0 * f64::consts::PI * self.index * thickness * cos_film
+                / CHANNEL_WAVELENGTHS[channel];
+
+            let s = airy(
+                fresnel_s(1.0, cos_incident, self.index, cos_film),
+                fresnel_s(self.index, cos_film, substrate, cos_substrate),
+                phase,
+            );
+            let p = airy(
+                fresnel_p(1.0, cos_incident, self.index, cos_film),
+                fresnel_p(self.index, cos_film, substrate, cos_substrate),
+                phase,
+            );
+            (0.5 * (s + p)).clamp(0.0, 1.0)
+        })
+    }
+}
+
+fn refracted_cos(cos_incident: f64, from: f64, to: f64) -> f64 {
+    let sin = (1.0 - cos_incident * cos_incident).max(0.0).sqrt() * from / to;
+    (1.0 - sin * sin).max(0.0).sqrt()
+}
+
+fn fresnel_s(n1: f64, cos1: f64, n2: f64, cos2: f64) -> f64 {
+    (n1 * cos1 - n2 * cos2) / (n1 * cos1 + n2 * cos2)
+}
+
+fn fresnel_p(n1: f64, cos1: f64, n2: f64, cos2: f64) -> f64 {
+    (n2 * cos1 - n1 * cos2) / (n2 * cos1 + n1 * cos2)
+}
+
+// Sums every reflection bouncing back and forth inside the film.
+fn airy(top: f64, bottom: f64, phase: f64) -> f64 {
+    let cross = 2.0 * top * bottom * phase.cos();
+    (top * top + bottom * bottom + cross) / (1.0 + top * top * bottom * bottom + cross)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn vanishing_film_leaves_the_substrate() {
+        let film = ThinFilm::new(Texture::constant(0.0), 1.33);
+        let glass = Vector3::new(0.04, 0.04, 0.04);
+        let reflectance = film.reflectance(1.0, &glass, &Point2::new(0.0, 0.0));
+        assert!((reflectance - glass).amax() < 1e-6);
+    }
+}
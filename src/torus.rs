@@ -0,0 +1,311 @@
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::shape::{Frame, Shape, Span, Surface, SurfaceSample, BIAS};
+use nalgebra::{Point2, Point3, Vector3};
+use std::f64;
+
+pub struct Torus {
+    center: Point3<f64>,
+    frame: Frame,
+    major_radius: f64,
+    minor_radius: f64,
+    material: Material,
+}
+
+impl Torus {
+    pub fn new(
+        center: Point3<f64>,
+        axis: Vector3<f64>,
+        major_radius: f64,
+        minor_radius: f64,
+        material: Material,
+    ) -> Self {
+        Torus {
+            center,
+            frame: Frame::new(center, axis),
+            major_radius,
+            minor_radius,
+            material,
+        }
+    }
+
+    fn distances(&self, ray: &Ray) -> Vec<f64> {
+        let local = self.frame.ray_to_local(ray);
+        let (o, d) = (local.origin.coords, local.direction);
+        let major2 = self.major_radius * self.major_radius;
+        let minor2 = self.minor_radius * self.minor_radius;
+
+        let e = o.dot(&o) + major2 - minor2;
+        let f = o.dot(&d);
+        let coefficients = [
+            e * e - 4.0 * major2 * (o.x * o.x + o.z * o.z),
+            4.0 * e * f - 8.0 * major2 * (o.x * d.x + o.z * d.z),
+            4.0 * f * f + 2.0 * e - 4.0 * major2 * (d.x * d.x + d.z * d.z),
+            4.0 * f,
+            1.0,
+        ];
+
+        let mut distances: Vec<f64> = quartic_roots(&coefficients)
+            .into_iter()
+            .map(|t| polish(&coefficients, t))
+            .filter(|t| t.is_finite())
+            .collect();
+        distances.sort_by(|a, b| a.total_cmp(b));
+        distances
+    }
+
+    fn contains(&self, ray: &Ray, distance: f64) -> bool {
+        let point = self.frame.point_to_local(&(ray.origin + ray.direction * distance));
+        let ring = (point.x * point.x + point.z * point.z).sqrt() - self.major_radius;
+        ring * ring + point.y * point.y < self.minor_radius * self.minor_radius
+    }
+}
+
+impl Shape for Torus {
+    fn intersection_distance(&self, ray: &Ray) -> f64 {
+        self.distances(ray)
+            .into_iter()
+            .find(|t| *t > BIAS)
+            .unwrap_or(f64::INFINITY)
+    }
+
+    fn surface(&self, ray: &Ray, distance: f64) -> Surface {
+        let point = self.frame.point_to_local(&(ray.origin + ray.direction * distance));
+        let ring = (point.x * point.x + point.z * point.z).sqrt();
+        let tube_center = Vector3::new(point.x, 0.0, point.z) * (self.major_radius / ring);
+        let normal = (point.coords - tube_center).normalize();
+        let u = (point.z.atan2(point.x) + f64::consts::PI) / (2.0 * f64::consts::PI);
+        let v = (point.y.atan2(ring - self.major_radius) + f64::consts::PI) / (2.0 * f64::consts::PI);
+        let normal = self.frame.vector_to_world(&normal);
+        Surface {
+            normal,
+            uv: Point2::new(u, v),
+            front_face: normal.dot(&ray.direction) < 0.0,
+        }
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn area(&self) -> f64 {
+        4.0 * f64::consts::PI * f64::consts::PI * self.major_radius * self.minor_radius
+    }
+
+    fn sample_surface(&self) -> Option<SurfaceSample> {
+        // The outer side of the tube has more area than the inner side, so
+        // reject tube angles in proportion to their distance from the axis.
+        let outer = self.major_radius + self.minor_radius;
+        let theta = loop {
+            let theta = 2.0 * f64::consts::PI * rand::random::<f64>();
+            let ring = self.major_radius + self.minor_radius * theta.cos();
+            if rand::random::<f64>() * outer <= ring {
+                break theta;
+            }
+        };
+        let phi = 2.0 * f64::consts::PI * rand::random::<f64>();
+
+        let normal = Vector3::new(theta.cos() * phi.cos(), theta.sin(), theta.cos() * phi.sin());
+        let ring = Vector3::new(phi.cos(), 0.0, phi.sin()) * self.major_radius;
+        let local_point = ring + normal * self.minor_radius;
+
+        Some(SurfaceSample {
+            point: self.center + self.frame.vector_to_world(&local_point),
+            normal: self.frame.vector_to_world(&normal),
+        })
+    }
+
+    // Roots don't always come in pairs, as a ray grazing the tube touches it
+    // without going in, so each gap between roots is checked for being
+    // inside instead.
+    fn spans(&self, ray: &Ray) -> Vec<Span> {
+        let mut spans: Vec<Span> = vec![];
+        for pair in self.distances(ray).windows(2) {
+            if !self.contains(ray, 0.5 * (pair[0] + pair[1])) {
+                continue;
+            }
+            match spans.last_mut() {
+                Some(last) if last.exit == pair[0] => last.exit = pair[1],
+                _ => spans.push(Span {
+                    enter: pair[0],
+                    exit: pair[1],
+                }),
+            }
+        }
+        spans
+    }
+}
+
+const EPSILON: f64 = 1e-9;
+
+fn is_zero(value: f64) -> bool {
+    value.abs() < EPSILON
+}
+
+// Newton steps to recover the precision lost by the closed form solution.
+fn polish(c: &[f64; 5], mut t: f64) -> f64 {
+    for _ in 0..2 {
+        let value = (((c[4] * t + c[3]) * t + c[2]) * t + c[1]) * t + c[0];
+        let slope = ((4.0 * c[4] * t + 3.0 * c[3]) * t + 2.0 * c[2]) * t + c[1];
+        if is_zero(slope) {
+            break;
+        }
+        t -= value / slope;
+    }
+    t
+}
+
+// Roots of c[0] + c[1]x + c[2]x^2 = 0, assuming c[2] == 1.
+fn normalised_quadratic_roots(c: [f64; 3]) -> Vec<f64> {
+    let p = c[1] / 2.0;
+    let q = c[0];
+    let d = p * p - q;
+    if is_zero(d) {
+        vec![-p]
+    } else if d < 0.0 {
+        vec![]
+    } else {
+        let d_root = d.sqrt();
+        vec![d_root - p, -d_root - p]
+    }
+}
+
+// Roots of c[0] + c[1]x + c[2]x^2 + c[3]x^3 = 0 using Cardano's formula.
+fn cubic_roots(c: [f64; 4]) -> Vec<f64> {
+    let a = c[2] / c[3];
+    let b = c[1] / c[3];
+    let cc = c[0] / c[3];
+
+    let sq_a = a * a;
+    let p = (-sq_a / 3.0 + b) / 3.0;
+    let q = (2.0 / 27.0 * a * sq_a - a * b / 3.0 + cc) / 2.0;
+    let cb_p = p * p * p;
+    let d = q * q + cb_p;
+
+    let roots = if is_zero(d) {
+        if is_zero(q) {
+            vec![0.0]
+        } else {
+            let u = (-q).cbrt();
+            vec![2.0 * u, -u]
+        }
+    } else if d < 0.0 {
+        let phi = (-q / (-cb_p).sqrt()).acos() / 3.0;
+        let t = 2.0 * (-p).sqrt();
+        vec![
+            t * phi.cos(),
+            -t * (phi + f64::consts::PI / 3.0).cos(),
+            -t * (phi - f64::consts::PI / 3.0).cos(),
+        ]
+    } else {
+        let d_root = d.sqrt();
+        vec![(d_root - q).cbrt() - (d_root + q).cbrt()]
+    };
+
+    roots.into_iter().map(|root| root - a / 3.0).collect()
+}
+
+// Roots of c[0] + c[1]x + ... + c[4]x^4 = 0 using Ferrari's method.
+fn quartic_roots(c: &[f64; 5]) -> Vec<f64> {
+    let a = c[3] / c[4];
+    let b = c[2] / c[4];
+    let cc = c[1] / c[4];
+    let d = c[0] / c[4];
+
+    // Substitute x = y - a/4 to eliminate the cubic term.
+    let sq_a = a * a;
+    let p = -3.0 / 8.0 * sq_a + b;
+    let q = sq_a * a / 8.0 - a * b / 2.0 + cc;
+    let r = -3.0 / 256.0 * sq_a * sq_a + sq_a * b / 16.0 - a * cc / 4.0 + d;
+
+    let roots = if is_zero(r) {
+        let mut roots = cubic_roots([q, p, 0.0, 1.0]);
+        roots.push(0.0);
+        roots
+    } else {
+        let z = cubic_roots([r * p / 2.0 - q * q / 8.0, -r, -p / 2.0, 1.0])[0];
+
+        let u = z * z - r;
+        let v = 2.0 * z - p;
+        if u < -EPSILON || v < -EPSILON {
+            return vec![];
+        }
+        let u = u.max(0.0).sqrt();
+        let v = if q < 0.0 { -v.max(0.0).sqrt() } else { v.max(0.0).sqrt() };
+
+        let mut roots = normalised_quadratic_roots([z - u, v, 1.0]);
+        roots.extend(normalised_quadratic_roots([z + u, -v, 1.0]));
+        roots
+    };
+
+    roots.into_iter().map(|root| root - a / 4.0).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use nalgebra::{Point3, Vector3};
+
+    #[test]
+    fn ray_along_a_diameter_hits_the_outer_tube_wall_first() {
+        let material = Material::new(
+            Vector3::new(1.0, 1.0, 1.0),
+            1.0,
+            0.0,
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.04, 0.04, 0.04),
+            0.0,
+            0.0,
+        );
+        let torus = Torus::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+            2.0,
+            0.5,
+            material,
+        );
+        let ray = Ray {
+            origin: Point3::new(-5.0, 0.0, 0.0),
+            direction: Vector3::new(1.0, 0.0, 0.0),
+            time: 0.0,
+        };
+
+        let distance = torus.intersection_distance(&ray);
+        assert!((distance - 2.5).abs() < 1e-9);
+
+        let surface = torus.surface(&ray, distance);
+        assert!((surface.normal - Vector3::new(-1.0, 0.0, 0.0)).norm() < 1e-9);
+    }
+
+    #[test]
+    fn grazing_rays_leave_the_spans_paired() {
+        let material = Material::new(
+            Vector3::new(1.0, 1.0, 1.0),
+            1.0,
+            0.0,
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.04, 0.04, 0.04),
+            0.0,
+            0.0,
+        );
+        let torus = Torus::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0), 2.0, 0.5, material);
+        let ray = |origin: Point3<f64>, direction: Vector3<f64>| Ray {
+            origin,
+            direction,
+            time: 0.0,
+        };
+
+        let through = torus.spans(&ray(Point3::new(-5.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0)));
+        assert_eq!(through.len(), 2);
+        assert!((through[0].enter - 2.5).abs() < 1e-9 && (through[0].exit - 3.5).abs() < 1e-9);
+        assert!((through[1].enter - 6.5).abs() < 1e-9 && (through[1].exit - 7.5).abs() < 1e-9);
+
+        // Touches the outside of the tube, and then the inside of the hole
+        // on the way through the tube.
+        let edge = torus.spans(&ray(Point3::new(2.5, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0)));
+        assert!(edge.is_empty());
+        let hole = torus.spans(&ray(Point3::new(1.5, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0)));
+        assert_eq!(hole.len(), 1);
+        assert!((hole[0].enter - 3.0).abs() < 1e-6 && (hole[0].exit - 7.0).abs() < 1e-6);
+    }
+}
@@ -1,8 +1,14 @@
+use crate::material::Material;
+use crate::medium::{Medium, MediumSample};
 use crate::ray::Ray;
-use crate::scene::Scene;
-use nalgebra::Point2;
+use crate::scene::{is_sampled_light, LightSample, Scene};
+use crate::shape::Shape;
+use crate::spectrum::{SpectralFilm, Wavelengths};
+use nalgebra::{Point2, Point3};
 use nalgebra::Vector3;
 
+const MAX_WALK_STEPS: usize = 1024;
+
 #[derive(Clone)]
 struct PixelInfo {
     color: Vector3<f64>,
@@ -16,11 +22,18 @@ pub struct Tracer {
     width: usize,
     height: usize,
     exposures: Vec<PixelInfo>,
-    index: usize
+    index: usize,
+    spectral: Option<SpectralFilm>,
 }
 
 impl Tracer {
+    // Scenes which ask to be rendered spectrally are.
     pub fn new(scene: Scene, bounces: u32, gamma: f64, width: usize, height: usize) -> Tracer {
+        let spectral = if scene.is_spectral() {
+            Some(SpectralFilm::new())
+        } else {
+            None
+        };
         Tracer {
             scene,
             bounces,
@@ -34,15 +47,52 @@ impl Tracer {
                 };
                 width * height
             ],
-            index: 0
+            index: 0,
+            spectral,
         }
     }
 
+    // Carries a few wavelengths along each path instead of red, green and
+    // blue, so that materials can treat each wavelength differently.
+    pub fn with_spectral_rendering(mut self) -> Self {
+        self.spectral = Some(SpectralFilm::new());
+        self
+    }
+
     pub fn update(&mut self, pixels: &mut [u8]) {
         let limit = (self.index / (self.width * self.height)) + 1;
         self.expose(limit, pixels);
     }
 
+    // Takes the same number of samples in every pixel at once, for images
+    // rendered to a fixed budget rather than refined as they are watched.
+    pub fn render(&mut self, samples: usize, pixels: &mut [u8]) {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let pixel = Point2::new(x, y);
+                for _ in 0..samples {
+                    let sample = self.trace(&pixel);
+                    let exposure = &mut self.exposures[x + y * self.width];
+                    exposure.color += sample;
+                    exposure.exposures += 1;
+                }
+                self.color_pixel(pixel, pixels);
+            }
+        }
+    }
+
+    // Focuses on whatever is seen at the pixel and starts the image over,
+    // giving the distance focused at.
+    pub fn focus_at(&mut self, x: usize, y: usize) -> Option<f64> {
+        let distance = self.scene.autofocus(x, y, self.width, self.height)?;
+        for exposure in &mut self.exposures {
+            exposure.color = Vector3::zeros();
+            exposure.exposures = 0;
+        }
+        self.index = 0;
+        Some(distance)
+    }
+
     fn pixel_for_index(&self, index: usize) -> Point2<usize> {
         let wrapped = index % (self.width * self.height);
         Point2::new(wrapped % self.width, wrapped / self.width)
@@ -61,8 +111,8 @@ impl Tracer {
 
         for _ in 0..limit {
             let sample = self.trace(&pixel);
-            self.exposures[rgba_index as usize].color += sample;
-            self.exposures[rgba_index as usize].exposures += 1;
+            self.exposures[rgba_index].color += sample;
+            self.exposures[rgba_index].exposures += 1;
         }
 
         self.color_pixel(pixel, pixels);
@@ -70,30 +120,164 @@ impl Tracer {
     }
 
     fn trace(&mut self, pixel: &Point2<usize>) -> Vector3<f64> {
-        let mut ray = self
-            .scene
-            .camera
-            .ray(pixel.x, pixel.y, self.width, self.height);
+        let mut ray = match self.scene.camera.ray(pixel.x, pixel.y, self.width, self.height) {
+            Some(ray) => ray,
+            None => return Vector3::zeros(),
+        };
+        // The whole path is traced through the scene as it stands at the
+        // moment the camera ray was sent.
+        let time = ray.time;
 
         let mut signal = Vector3::new(1.0, 1.0, 1.0);
         let mut energy = Vector3::new(0.0, 0.0, 0.0);
+        let mut wavelengths = self.spectral.as_ref().map(|_| Wavelengths::sample());
+        let mut medium = self.scene.medium.clone();
+        // The density with which the current direction was sampled, and the
+        // point it was sampled from.
+        let mut scatter_pdf = None;
+
+        // Passing through null surfaces doesn't count as a bounce.
+        let mut bounces = 0;
+        while bounces < self.bounces {
+            let intersection = self.scene.intersect(&ray);
 
-        for _ in 0..self.bounces {
-            if let Some(intersect) = self.scene.intersect(&ray) {
-                if let Some(light) = intersect.material.emit(&intersect.normal, &ray.direction) {
-                    energy += light.component_mul(&signal);
+            if let Some(current) = &medium {
+                let surface_distance = intersection.as_ref().map_or(f64::INFINITY, |i| i.distance);
+                match current.sample_distance(&ray, surface_distance) {
+                    MediumSample::Scatter { distance, weight } => {
+                        signal = signal.component_mul(&spectral(&wavelengths, &weight));
+                        let point = ray.origin + ray.direction * distance;
+
+                        if let Some(light) = self.scene.sample_light(&point, time, medium.clone()) {
+                            let phase = current.phase(ray.direction.dot(&light.direction));
+                            let weight = power_heuristic(light.pdf, phase);
+                            energy += signal.component_mul(&spectral(&wavelengths, &light.radiance))
+                                * (phase * weight);
+                        }
+
+                        let direction = current.sample_phase(&ray.direction);
+                        scatter_pdf = Some((current.phase(ray.direction.dot(&direction)), point));
+                        ray = Ray {
+                            origin: point,
+                            direction,
+                            time,
+                        };
+
+                        let max = signal.norm();
+                        if dies(&mut signal, max) {
+                            break;
+                        }
+                        bounces += 1;
+                        continue;
+                    }
+                    MediumSample::Pass { weight } => {
+                        signal = signal.component_mul(&spectral(&wavelengths, &weight))
+                    }
                 }
+            }
 
-                if let Some(sample) =
-                    intersect
-                        .material
-                        .bsdf(&intersect.normal, &ray.direction, intersect.distance)
+            if let Some(intersect) = intersection {
+                if let Some(light) = intersect
+                    .material
+                    .emit(intersect.front_face, intersect.object.area())
                 {
+                    // Emitters reached straight after a diffuse bounce or a
+                    // scattering event could also have been found by sampling
+                    // the lights directly.
+                    let weight = match scatter_pdf {
+                        Some((bsdf_pdf, origin)) if is_sampled_light(intersect.object) => {
+                            let light_pdf = self.scene.light_pdf(
+                                intersect.object,
+                                (intersect.hit - origin).norm(),
+                                intersect.normal.dot(&ray.direction),
+                            );
+                            power_heuristic(bsdf_pdf, light_pdf)
+                        }
+                        _ => 1.0,
+                    };
+                    energy += spectral(&wavelengths, &light).component_mul(&signal) * weight;
+                }
+
+                if let Some(sample) = intersect.material.bsdf(
+                    &intersect.normal,
+                    &intersect.tangent,
+                    &ray.direction,
+                    &intersect.uv,
+                    intersect.distance,
+                    wavelengths.as_ref().map(Wavelengths::hero),
+                ) {
+                    // Light seen through null surfaces could still have been
+                    // sampled from the last scattering point.
+                    if !intersect.material.is_null() || intersect.distance.is_infinite() {
+                        scatter_pdf = None;
+                        bounces += 1;
+                    }
+                    if sample.diffuse {
+                        energy += signal.component_mul(&self.direct_light(
+                            &intersect.material,
+                            self.scene.sample_light(&intersect.hit, time, medium.clone()),
+                            &intersect.normal,
+                            &-ray.direction,
+                            &wavelengths,
+                        ));
+                        scatter_pdf = Some((
+                            intersect.material.scatter_pdf(&intersect.normal, &-ray.direction, &sample.direction),
+                            intersect.hit,
+                        ));
+                    }
+
+                    if let Some(interior) = intersect.material.interior() {
+                        medium = if sample.direction.dot(&intersect.normal) < 0.0 {
+                            Some(interior)
+                        } else {
+                            self.scene.medium.clone()
+                        };
+                    }
+
+                    let transmitted = sample.direction.dot(&intersect.normal) * ray.direction.dot(&intersect.normal) > 0.0;
                     ray = Ray {
                         origin: intersect.hit,
                         direction: sample.direction,
+                        time,
                     };
-                    signal = signal.component_mul(&sample.signal);
+                    signal = signal.component_mul(&spectral(&wavelengths, &sample.signal));
+                    if let Some(wavelengths) = &mut wavelengths {
+                        if transmitted && intersect.material.is_dispersive() {
+                            wavelengths.keep_hero(&mut signal);
+                        }
+                    }
+
+                    // Light which gets into a subsurface material wanders
+                    // around inside until it finds its way back out, then
+                    // leaves as if from a diffuse surface. The walk has
+                    // already coloured it, so that surface is white.
+                    if let Some(subsurface) = intersect.material.subsurface() {
+                        if sample.direction.dot(&intersect.normal) < 0.0 {
+                            let (exit, normal, weight) =
+                                match random_walk(intersect.object, subsurface, ray) {
+                                    Some(walk) => walk,
+                                    None => break,
+                                };
+                            signal = signal.component_mul(&spectral(&wavelengths, &weight));
+                            let surface = white_diffuse();
+                            energy += signal.component_mul(&self.direct_light(
+                                &surface,
+                                self.scene.sample_light(&exit, time, medium.clone()),
+                                &normal,
+                                &normal,
+                                &wavelengths,
+                            ));
+
+                            let leaving = surface.diffused(&normal, &normal);
+                            scatter_pdf = Some((cosine_pdf(&normal, &leaving.direction), exit));
+                            ray = Ray {
+                                origin: exit,
+                                direction: leaving.direction,
+                                time,
+                            };
+                            signal = signal.component_mul(&spectral(&wavelengths, &leaving.signal));
+                        }
+                    }
                 } else {
                     break;
                 }
@@ -103,12 +287,35 @@ impl Tracer {
                     break;
                 }
             } else {
-                energy += self.scene.bg(&ray).component_mul(&signal);
+                energy += spectral(&wavelengths, &self.scene.bg(&ray)).component_mul(&signal);
                 break;
             }
         }
 
-        energy
+        match (&self.spectral, &wavelengths) {
+            (Some(film), Some(wavelengths)) => film.to_rgb(wavelengths, &energy),
+            _ => energy,
+        }
+    }
+
+    // Light arriving straight from an emitter sampled at a diffuse point,
+    // weighted against finding the same light by following a diffuse bounce.
+    fn direct_light(
+        &self,
+        material: &Material,
+        light: Option<LightSample>,
+        normal: &Vector3<f64>,
+        view: &Vector3<f64>,
+        wavelengths: &Option<Wavelengths>,
+    ) -> Vector3<f64> {
+        match light {
+            Some(light) => {
+                let response = material.diffuse_response(normal, view, &light.direction);
+                let weight = power_heuristic(light.pdf, material.scatter_pdf(normal, view, &light.direction));
+                spectral(wavelengths, &response).component_mul(&spectral(wavelengths, &light.radiance)) * weight
+            }
+            None => Vector3::zeros(),
+        }
     }
 
     fn color_pixel(&mut self, pixel: Point2<usize>, pixels: &mut [u8]) {
@@ -127,6 +334,69 @@ impl Tracer {
     }
 }
 
+// Scatters a ray around inside a closed object until it crosses the boundary,
+// giving the exit point, the outward normal there and the weight of the walk.
+// Walks which go on too long are treated as absorbed.
+fn random_walk(
+    object: &dyn Shape,
+    medium: &Medium,
+    mut ray: Ray,
+) -> Option<(Point3<f64>, Vector3<f64>, Vector3<f64>)> {
+    let mut weight = Vector3::new(1.0, 1.0, 1.0);
+    for _ in 0..MAX_WALK_STEPS {
+        let boundary = object.intersection_distance(&ray);
+        if boundary.is_infinite() {
+            return None;
+        }
+
+        match medium.sample_distance(&ray, boundary) {
+            MediumSample::Scatter { distance, weight: step } => {
+                weight.component_mul_assign(&step);
+                ray = Ray {
+                    origin: ray.origin + ray.direction * distance,
+                    direction: medium.sample_phase(&ray.direction),
+                    time: ray.time,
+                };
+            }
+            MediumSample::Pass { weight: step } => {
+                weight.component_mul_assign(&step);
+                let normal = object.surface(&ray, boundary).normal;
+                let outward = if normal.dot(&ray.direction) > 0.0 { normal } else { -normal };
+                return Some((ray.origin + ray.direction * boundary, outward, weight));
+            }
+        }
+    }
+    None
+}
+
+fn white_diffuse() -> Material {
+    let white = Vector3::new(1.0, 1.0, 1.0);
+    Material::new(white, 1.0, 0.0, Vector3::zeros(), Vector3::zeros(), 0.0, 0.0)
+}
+
+// Colours are given in RGB, so spectral paths need them as a spectrum.
+fn spectral(wavelengths: &Option<Wavelengths>, rgb: &Vector3<f64>) -> Vector3<f64> {
+    match wavelengths {
+        Some(wavelengths) => wavelengths.upsample(rgb),
+        None => *rgb,
+    }
+}
+
+fn cosine_pdf(normal: &Vector3<f64>, direction: &Vector3<f64>) -> f64 {
+    f64::max(normal.dot(direction), 0.0) / std::f64::consts::PI
+}
+
+fn power_heuristic(pdf: f64, other_pdf: f64) -> f64 {
+    let (a, b) = (pdf * pdf, other_pdf * other_pdf);
+    if a.is_infinite() {
+        1.0
+    } else if a + b == 0.0 {
+        0.0
+    } else {
+        a / (a + b)
+    }
+}
+
 fn dies(v: &mut Vector3<f64>, chance: f64) -> bool {
     if rand::random::<f64>() > chance {
         true
@@ -135,3 +405,80 @@ fn dies(v: &mut Vector3<f64>, chance: f64) -> bool {
         false
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::camera::View;
+    use crate::material::Emission;
+    use crate::orthographic::Orthographic;
+    use crate::sphere::Sphere;
+
+    fn sphere_scene(material: Material) -> Tracer {
+        let view = View::look_at(Point3::new(0.0, 0.0, 5.0), Point3::origin(), Vector3::new(0.0, 1.0, 0.0));
+        let sphere = Sphere::new(Point3::origin(), 1.0, material);
+        let black = Material::new(Vector3::zeros(), 1.0, 0.0, Vector3::zeros(), Vector3::zeros(), 0.0, 0.0);
+        let sky = black.emitting(Emission::Radiance(Vector3::new(1.0, 1.0, 1.0)), true);
+        let objects: Vec<Box<dyn Shape>> = vec![Box::new(sphere), Box::new(Sphere::new(Point3::origin(), 50.0, sky))];
+        let scene = Scene::new(objects, Orthographic::new(view, 0.5));
+        Tracer::new(scene, 10, 2.2, 1, 1)
+    }
+
+    #[test]
+    fn walks_through_a_scattering_only_medium_lose_no_energy() {
+        let material = Material::new(
+            Vector3::new(1.0, 1.0, 1.0),
+            1.0,
+            0.0,
+            Vector3::zeros(),
+            Vector3::zeros(),
+            0.0,
+            0.0,
+        )
+        .with_subsurface(Vector3::new(1.0, 1.0, 1.0), Vector3::new(0.1, 0.1, 0.1));
+        let sphere = Sphere::new(Point3::origin(), 1.0, material.clone());
+        let medium = material.subsurface().unwrap();
+
+        for _ in 0..200 {
+            let ray = Ray {
+                origin: Point3::new(0.0, 0.0, 1.0),
+                direction: Vector3::new(0.0, 0.0, -1.0),
+                time: 0.0,
+            };
+            let (exit, normal, weight) = random_walk(&sphere, medium, ray).unwrap();
+            assert!((exit.coords.norm() - 1.0).abs() < 1e-6);
+            assert!((normal - exit.coords).norm() < 1e-6);
+            assert!((weight - Vector3::new(1.0, 1.0, 1.0)).norm() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn light_leaves_a_subsurface_walk_coloured_only_by_the_medium() {
+        let plain = |color: f64| {
+            let grey = Vector3::new(color, color, color);
+            Material::new(grey, 1.0, 0.0, Vector3::zeros(), Vector3::zeros(), 0.0, 0.0)
+        };
+        let average = |mut tracer: Tracer| {
+            let samples = 4000;
+            let total = (0..samples).fold(Vector3::zeros(), |total, _| total + tracer.trace(&Point2::new(0, 0)));
+            total / samples as f64
+        };
+
+        // Lit evenly from all around, a dark material full of a medium which
+        // never absorbs looks like a white diffuse surface.
+        let diffuse = average(sphere_scene(plain(1.0)));
+        let subsurface = average(sphere_scene(
+            plain(0.2).with_subsurface(Vector3::new(1.0, 1.0, 1.0), Vector3::new(0.1, 0.1, 0.1)),
+        ));
+        assert!(diffuse.x > 0.0);
+        assert!((subsurface.x - diffuse.x).abs() < 0.05 * diffuse.x);
+    }
+
+    #[test]
+    fn scenes_can_ask_for_spectral_rendering() {
+        let view = View::look_at(Point3::new(0.0, 0.0, 5.0), Point3::origin(), Vector3::new(0.0, 1.0, 0.0));
+        let scene = || Scene::new(vec![], Orthographic::new(view, 1.0));
+        assert!(Tracer::new(scene(), 10, 2.2, 1, 1).spectral.is_none());
+        assert!(Tracer::new(scene().with_spectral_rendering(), 10, 2.2, 1, 1).spectral.is_some());
+    }
+}
@@ -0,0 +1,99 @@
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::shape::{facing, Shape, Surface, SurfaceSample, BIAS};
+use nalgebra::{Point2, Point3, Vector3};
+use std::f64;
+
+pub struct Triangle {
+    a: Point3<f64>,
+    edge1: Vector3<f64>,
+    edge2: Vector3<f64>,
+    normal: Vector3<f64>,
+    material: Material,
+}
+
+impl Triangle {
+    pub fn new(a: Point3<f64>, b: Point3<f64>, c: Point3<f64>, material: Material) -> Self {
+        let edge1 = b - a;
+        let edge2 = c - a;
+        Triangle {
+            a,
+            edge1,
+            edge2,
+            normal: edge1.cross(&edge2).normalize(),
+            material,
+        }
+    }
+
+    // Moller-Trumbore, returning the distance and barycentric coordinates.
+    fn barycentric(&self, ray: &Ray) -> Option<(f64, Point2<f64>)> {
+        let p = ray.direction.cross(&self.edge2);
+        let det = self.edge1.dot(&p);
+        if det.abs() < f64::EPSILON {
+            return None;
+        }
+
+        let inverse = 1.0 / det;
+        let s = ray.origin - self.a;
+        let u = s.dot(&p) * inverse;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+
+        let q = s.cross(&self.edge1);
+        let v = ray.direction.dot(&q) * inverse;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+
+        Some((self.edge2.dot(&q) * inverse, Point2::new(u, v)))
+    }
+}
+
+impl Shape for Triangle {
+    fn intersection_distance(&self, ray: &Ray) -> f64 {
+        match self.barycentric(ray) {
+            Some((t, _)) if t > BIAS => t,
+            _ => f64::INFINITY,
+        }
+    }
+
+    fn surface(&self, ray: &Ray, distance: f64) -> Surface {
+        let point = ray.origin + ray.direction * distance;
+        let offset = point - self.a;
+        let d00 = self.edge1.dot(&self.edge1);
+        let d01 = self.edge1.dot(&self.edge2);
+        let d11 = self.edge2.dot(&self.edge2);
+        let d20 = offset.dot(&self.edge1);
+        let d21 = offset.dot(&self.edge2);
+        let denominator = d00 * d11 - d01 * d01;
+
+        let (normal, front_face) = facing(self.normal, ray);
+        Surface {
+            normal,
+            uv: Point2::new(
+                (d11 * d20 - d01 * d21) / denominator,
+                (d00 * d21 - d01 * d20) / denominator,
+            ),
+            front_face,
+        }
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn area(&self) -> f64 {
+        self.edge1.cross(&self.edge2).norm() * 0.5
+    }
+
+    fn sample_surface(&self) -> Option<SurfaceSample> {
+        let root = rand::random::<f64>().sqrt();
+        let u = 1.0 - root;
+        let v = rand::random::<f64>() * root;
+        Some(SurfaceSample {
+            point: self.a + self.edge1 * u + self.edge2 * v,
+            normal: self.normal,
+        })
+    }
+}
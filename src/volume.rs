@@ -0,0 +1,219 @@
+use nalgebra::{Point3, Vector3};
+use std::convert::TryInto;
+
+const MAGIC: &[u8; 4] = b"VOXL";
+
+// Densities sampled on a regular grid spanning an axis aligned box, with the
+// x index varying fastest.
+pub struct VoxelGrid {
+    min: Point3<f64>,
+    max: Point3<f64>,
+    size: [usize; 3],
+    data: Vec<f64>,
+    max_density: f64,
+}
+
+impl VoxelGrid {
+    pub fn new(min: Point3<f64>, max: Point3<f64>, size: [usize; 3], data: Vec<f64>) -> Self {
+        assert_eq!(data.len(), size[0] * size[1] * size[2]);
+        let max_density = data.iter().cloned().fold(0.0, f64::max);
+        VoxelGrid {
+            min,
+            max,
+            size,
+            data,
+            max_density,
+        }
+    }
+
+    // Reads a little endian grid file: the magic bytes "VOXL", three u32
+    // dimensions, six f32 bounds (min then max corner) and one f32 density
+    // per voxel.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < 40 || &bytes[0..4] != MAGIC {
+            return None;
+        }
+
+        let word = |index: usize| -> [u8; 4] { bytes[index..index + 4].try_into().unwrap() };
+        let int = |index: usize| u32::from_le_bytes(word(index)) as usize;
+        let float = |index: usize| f64::from(f32::from_le_bytes(word(index)));
+
+        let size = [int(4), int(8), int(12)];
+        let min = Point3::new(float(16), float(20), float(24));
+        let max = Point3::new(float(28), float(32), float(36));
+
+        // Tracking only makes progress through grids which enclose some
+        // space and hold finite densities.
+        let finite = |point: &Point3<f64>| point.iter().all(|v| v.is_finite());
+        if !finite(&min) || !finite(&max) || (0..3).any(|axis| min[axis] >= max[axis]) {
+            return None;
+        }
+        let count = size[0].checked_mul(size[1])?.checked_mul(size[2])?;
+        if count == 0 || bytes.len() != count.checked_mul(4)?.checked_add(40)? {
+            return None;
+        }
+        let data: Vec<f64> = (0..count).map(|i| float(40 + i * 4)).collect();
+        if data.iter().any(|density| !density.is_finite()) {
+            return None;
+        }
+        let data = data.into_iter().map(|density| density.max(0.0)).collect();
+
+        Some(VoxelGrid::new(min, max, size, data))
+    }
+
+    pub fn bounds(&self) -> (Point3<f64>, Point3<f64>) {
+        (self.min, self.max)
+    }
+
+    pub fn max_density(&self) -> f64 {
+        self.max_density
+    }
+
+    // Trilinearly interpolated density, treating voxels as samples at their
+    // centres. Points outside the grid are empty.
+    pub fn density(&self, point: &Point3<f64>) -> f64 {
+        let extent = self.max - self.min;
+        let local = (point - self.min).component_div(&extent);
+        if local.min() < 0.0 || local.max() > 1.0 {
+            return 0.0;
+        }
+
+        let mut index = [0; 3];
+        let mut fraction = [0.0; 3];
+        for axis in 0..3 {
+            let cell = (local[axis] * self.size[axis] as f64 - 0.5).max(0.0);
+            index[axis] = (cell as usize).min(self.size[axis] - 1);
+            fraction[axis] = (cell - index[axis] as f64).min(1.0);
+        }
+
+        let mut density = 0.0;
+        for corner in 0..8 {
+            let mut weight = 1.0;
+            let mut voxel = [0; 3];
+            for axis in 0..3 {
+                let upper = corner >> axis & 1 == 1;
+                voxel[axis] = (index[axis] + upper as usize).min(self.size[axis] - 1);
+                weight *= if upper { fraction[axis] } else { 1.0 - fraction[axis] };
+            }
+            density += weight * self.voxel(voxel);
+        }
+        density
+    }
+
+    fn voxel(&self, [x, y, z]: [usize; 3]) -> f64 {
+        self.data[x + self.size[0] * (y + self.size[1] * z)]
+    }
+}
+
+// Fractal gradient noise, thresholded so that only its peaks hold any
+// density. Higher thresholds give sparser, more broken up clouds.
+#[derive(Copy, Clone)]
+pub struct Noise {
+    frequency: f64,
+    octaves: usize,
+    threshold: f64,
+}
+
+impl Noise {
+    pub fn new(frequency: f64, octaves: usize, threshold: f64) -> Self {
+        Noise {
+            frequency,
+            octaves,
+            threshold,
+        }
+    }
+
+    pub fn density(&self, point: &Point3<f64>) -> f64 {
+        let mut p = point.coords * self.frequency;
+        let mut amplitude = 0.5;
+        let mut total = 0.0;
+        for _ in 0..self.octaves {
+            total += amplitude * gradient_noise(&p);
+            p *= 2.0;
+            amplitude *= 0.5;
+        }
+
+        let value = 0.5 + total;
+        ((value - self.threshold) / (1.0 - self.threshold)).clamp(0.0, 1.0)
+    }
+}
+
+fn gradient_noise(p: &Vector3<f64>) -> f64 {
+    let cell = p.map(f64::floor);
+    let offset = p - cell;
+    let fade = offset.map(|t| t * t * t * (t * (t * 6.0 - 15.0) + 10.0));
+
+    let mut value = 0.0;
+    for corner in 0..8 {
+        let step = Vector3::new(
+            (corner & 1) as f64,
+            (corner >> 1 & 1) as f64,
+            (corner >> 2 & 1) as f64,
+        );
+        let weight = (0..3).fold(1.0, |weight, axis| {
+            weight * if step[axis] == 1.0 { fade[axis] } else { 1.0 - fade[axis] }
+        });
+        value += weight * gradient(&(cell + step)).dot(&(offset - step));
+    }
+    value
+}
+
+// A pseudo random unit length gradient for each lattice point.
+fn gradient(lattice: &Vector3<f64>) -> Vector3<f64> {
+    let mut hash = (lattice.x as i64 as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        ^ (lattice.y as i64 as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F)
+        ^ (lattice.z as i64 as u64).wrapping_mul(0x1656_67B1_9E37_79F9);
+    hash ^= hash >> 31;
+    hash = hash.wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    hash ^= hash >> 29;
+
+    let z = (hash & 0xFFFF) as f64 / 32767.5 - 1.0;
+    let phi = (hash >> 16 & 0xFFFF) as f64 / 65536.0 * 2.0 * std::f64::consts::PI;
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    Vector3::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn grid_reads_from_bytes() {
+        let mut bytes = MAGIC.to_vec();
+        for size in &[2u32, 1, 1] {
+            bytes.extend_from_slice(&size.to_le_bytes());
+        }
+        for bound in &[0f32, 0.0, 0.0, 2.0, 1.0, 1.0, 0.0, 4.0] {
+            bytes.extend_from_slice(&bound.to_le_bytes());
+        }
+
+        let grid = VoxelGrid::from_bytes(&bytes).unwrap();
+        assert_eq!(grid.max_density(), 4.0);
+        assert_eq!(grid.density(&Point3::new(0.5, 0.5, 0.5)), 0.0);
+        assert_eq!(grid.density(&Point3::new(1.0, 0.5, 0.5)), 2.0);
+        assert_eq!(grid.density(&Point3::new(1.5, 0.5, 0.5)), 4.0);
+        assert_eq!(grid.density(&Point3::new(3.0, 0.5, 0.5)), 0.0);
+        assert!(VoxelGrid::from_bytes(&bytes[..bytes.len() - 1]).is_none());
+
+        // Sizes too large to count, let alone hold.
+        for size in 0..3 {
+            bytes[4 + size * 4..8 + size * 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        }
+        assert!(VoxelGrid::from_bytes(&bytes).is_none());
+        bytes[4..16].copy_from_slice(&[2, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0]);
+        assert!(VoxelGrid::from_bytes(&bytes).is_some());
+
+        // Densities which would leave tracking stuck, and boxes with no room
+        // inside.
+        let mut broken = bytes.clone();
+        broken[44..48].copy_from_slice(&f32::INFINITY.to_le_bytes());
+        assert!(VoxelGrid::from_bytes(&broken).is_none());
+        broken[44..48].copy_from_slice(&f32::NAN.to_le_bytes());
+        assert!(VoxelGrid::from_bytes(&broken).is_none());
+        let mut flat = bytes.clone();
+        flat[28..32].copy_from_slice(&0f32.to_le_bytes());
+        assert!(VoxelGrid::from_bytes(&flat).is_none());
+        flat[28..32].copy_from_slice(&(-1f32).to_le_bytes());
+        assert!(VoxelGrid::from_bytes(&flat).is_none());
+    }
+}